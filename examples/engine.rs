@@ -0,0 +1,89 @@
+//! A one-ply engine that grabs the most valuable piece it can, usable from any UCI GUI.
+
+use anyhow::Result;
+use shakmaty::{CastlingMode, Chess, Position, Role, fen::Fen, uci::UciMove};
+
+use uci::{
+    search::{BestMove, Info, Score},
+    server::{self, EngineBackend, Reporter},
+};
+
+struct Greedy;
+
+fn value(role: Role) -> i32 {
+    match role {
+        Role::Pawn => 100,
+        Role::Knight | Role::Bishop => 300,
+        Role::Rook => 500,
+        Role::Queen => 900,
+        Role::King => 0,
+    }
+}
+
+fn setup(position: &server::Position) -> Result<Chess> {
+    let mut pos = match &position.fen {
+        None => Chess::default(),
+        Some(fen) => fen.parse::<Fen>()?.into_position(CastlingMode::Standard)?,
+    };
+    for mv in &position.moves {
+        let mv = mv.parse::<UciMove>()?.to_move(&pos)?;
+        pos.play_unchecked(&mv);
+    }
+    Ok(pos)
+}
+
+impl EngineBackend for Greedy {
+    fn name(&self) -> &str {
+        "Greedy"
+    }
+
+    fn author(&self) -> &str {
+        "uci"
+    }
+
+    fn search(
+        &mut self,
+        position: &server::Position,
+        _go: &server::Go,
+        reporter: &Reporter,
+    ) -> BestMove {
+        let Ok(pos) = setup(position) else {
+            reporter.string("invalid position");
+            return BestMove {
+                best: "0000".into(),
+                ponder: None,
+            };
+        };
+
+        let best = pos
+            .legal_moves()
+            .into_iter()
+            .max_by_key(|m| m.capture().map(value).unwrap_or_default());
+
+        let Some(best) = best else {
+            return BestMove {
+                best: "0000".into(),
+                ponder: None,
+            };
+        };
+        let uci = best.to_uci(CastlingMode::Standard).to_string();
+
+        reporter.info(&Info {
+            depth: 1,
+            score: Some(Score::Cp(best.capture().map(value).unwrap_or_default())),
+            nodes: Some(pos.legal_moves().len() as u64),
+            pv: vec![uci.clone()],
+            ..Default::default()
+        });
+
+        BestMove {
+            best: uci,
+            ponder: None,
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    server::run_stdio(Greedy).await
+}
@@ -0,0 +1,23 @@
+use anyhow::{Context, Result};
+
+use uci::{
+    engine::{Engine, Go},
+    epd,
+};
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let path = std::env::args().nth(1).unwrap_or("examples/wac.epd".into());
+    let suite = epd::parse_suite(&std::fs::read_to_string(&path).context("failed to read suite")?)?;
+
+    let mut engine = Engine::new("stockfish")?;
+    engine.uci().await?;
+    engine.opts(&[("Threads", "4")]).await?;
+    engine.isready().await?;
+
+    let report = epd::run(&mut engine, &suite, &Go::default().movetime(1000)).await?;
+    report.write_csv(std::io::stdout())?;
+    eprintln!("solved {}/{}", report.solved(), report.total());
+
+    Ok(())
+}
@@ -1,18 +1,15 @@
-use std::sync::Arc;
-
 use anyhow::Result;
 use pgn_reader::{BufferedReader, RawHeader, SanPlus, Skip, Visitor};
-use shakmaty::{CastlingMode, Chess, EnPassantMode, FromSetup, Position, Setup, fen::Fen};
+use shakmaty::{CastlingMode, Chess, EnPassantMode, Position, fen::Fen};
 
-use uci::{
-    engine::{Engine, Go},
-    search::Search,
-};
+use uci::engine::Engine;
 
+#[allow(dead_code)]
 struct LastPosition {
     pos: Chess,
 }
 
+#[allow(dead_code)]
 impl LastPosition {
     fn new() -> LastPosition {
         LastPosition {
@@ -48,10 +45,11 @@ impl Visitor for LastPosition {
     }
 
     fn end_game(&mut self) -> Self::Result {
-        ::std::mem::replace(&mut self.pos, Chess::default())
+        std::mem::take(&mut self.pos)
     }
 }
 
+#[allow(dead_code)]
 #[derive(Debug)]
 struct Move {
     san: String,
@@ -1,16 +1,19 @@
+use std::fmt::Write;
 use std::process::Stdio;
 use std::time::Duration;
-use std::{fmt::Write, path::Path};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use tokio::{
     io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines},
     process::{Child, ChildStdin, ChildStdout},
+    select,
     sync::{mpsc, oneshot},
     task,
 };
 use uci::search::{BestMove, Info, Search};
 
+/// `SetOption` is not sent by `main` yet.
+#[allow(dead_code)]
 #[derive(Debug)]
 enum Command {
     IsReady(oneshot::Sender<()>),
@@ -37,8 +40,8 @@ struct Engine {
 
 impl Engine {
     async fn run(&mut self) -> Result<()> {
-        let mut stdin = self.child.stdin.take().unwrap();
-        let stdout = self.child.stdout.take().unwrap();
+        let mut stdin = self.child.stdin.take().context("no stdin")?;
+        let stdout = self.child.stdout.take().context("no stdout")?;
 
         let mut reader = BufReader::new(stdout).lines();
 
@@ -52,7 +55,7 @@ impl Engine {
                     self.isready(&mut stdin, &mut reader).await?;
                     _ = ack.send(());
                 }
-                Command::SetOption { name, value } => todo!(),
+                Command::SetOption { .. } => todo!(),
                 Command::Go { job, tx } => {
                     let mut position = "position".to_string();
 
@@ -70,23 +73,39 @@ impl Engine {
                         .write_all(format!("go depth {}\n", job.depth).as_bytes())
                         .await?;
 
-                    while let Some(line) = reader.next_line().await? {
-                        if line.starts_with("info depth") {
-                            let info = line.parse::<Info>().unwrap();
-                            _ = tx.send(Search::Info(info)).await;
-                        } else if line.starts_with("bestmove") {
-                            let bestmove = line.parse::<BestMove>().unwrap();
-                            _ = tx.send(Search::BestMove(bestmove)).await;
-                            break;
+                    // Commands arriving during the search are handled here, so `stop` can
+                    // interrupt it; the search still ends with its `bestmove`.
+                    let mut stopped = None;
+                    loop {
+                        select! {
+                            line = reader.next_line() => {
+                                let Some(line) = line? else { break };
+                                if line.starts_with("info depth") {
+                                    let info = line.parse::<Info>()?;
+                                    _ = tx.send(Search::Info(info)).await;
+                                } else if line.starts_with("bestmove") {
+                                    let bestmove = line.parse::<BestMove>()?;
+                                    _ = tx.send(Search::BestMove(bestmove)).await;
+                                    break;
+                                }
+                            }
+                            Some(cmd) = self.rx.recv(), if stopped.is_none() => match cmd {
+                                Command::Stop(ack) => {
+                                    stdin.write_all(b"stop\n").await?;
+                                    stopped = Some(ack);
+                                }
+                                other => println!("ignoring {other} during search"),
+                            },
                         }
                     }
+                    if let Some(ack) = stopped {
+                        _ = ack.send(());
+                        println!("stopped!");
+                    }
                 }
                 Command::Stop(ack) => {
-                    stdin.write_all(b"stop\nisready\n").await?;
-                    stdin.write_all(b"isready\n").await?;
-                    self.wait(&mut reader, "readyok").await?;
+                    self.isready(&mut stdin, &mut reader).await?;
                     _ = ack.send(());
-                    eprintln!("stopped!");
                 }
             }
         }
@@ -163,6 +182,7 @@ impl Searcher {
 
 pub struct Handle {
     tx: mpsc::Sender<Command>,
+    task: task::JoinHandle<Result<()>>,
 }
 
 impl Handle {
@@ -195,20 +215,16 @@ fn spawn() -> Result<Handle> {
         .spawn()?;
 
     let mut engine = Engine { child, rx };
-    task::spawn(async move {
-        if let Err(e) = engine.run().await {
-            eprintln!("engine error: {e}");
-        }
-    });
+    let task = task::spawn(async move { engine.run().await });
 
-    Ok(Handle { tx })
+    Ok(Handle { tx, task })
 }
 
 async fn search(mut searcher: Searcher) {
     while let Some(search) = searcher.next().await {
         match search {
-            Search::Info(info) => eprintln!("{info:?}"),
-            Search::BestMove(bestmove) => eprintln!("{bestmove:?}"),
+            Search::Info(info) => println!("{info:?}"),
+            Search::BestMove(bestmove) => println!("{bestmove:?}"),
         }
     }
 }
@@ -228,7 +244,7 @@ async fn main() -> Result<()> {
     tokio::time::sleep(Duration::from_secs(2)).await;
     engine.stop().await?;
 
-    // search(searcher).await;
+    search(searcher).await;
 
     // let job = Go::new().moves(&["d2d4", "g8f6"]).depth(10);
     // let searcher = engine.go(job).await?;
@@ -307,10 +323,10 @@ async fn main() -> Result<()> {
     // task::spawn(async move {
     //     while let Some(line) = reader.next_line().await.unwrap() {
     //         if line.starts_with("info depth") {
-    //             let info = line.parse::<Info>().unwrap();
+    //             let info = line.parse::<Info>()?;
     //             _ = tx.send(Search::Info(info)).await;
     //         } else if line.starts_with("bestmove") {
-    //             let bestmove = line.parse::<BestMove>().unwrap();
+    //             let bestmove = line.parse::<BestMove>()?;
     //             _ = tx.send(Search::BestMove(bestmove)).await;
     //             break;
     //         }
@@ -321,5 +337,10 @@ async fn main() -> Result<()> {
     //     println!("{search:?}");
     // }
 
+    // The engine task ends once every handle is gone, with the first error it ran into.
+    let Handle { tx, task } = engine;
+    drop(tx);
+    task.await??;
+
     Ok(())
 }
@@ -4,7 +4,7 @@ use anyhow::Result;
 use tokio::{select, time};
 
 use uci::{
-    engine::{Engine, Go, search},
+    engine::{Engine, Go},
     search::Search,
 };
 
@@ -21,26 +21,24 @@ async fn main() -> Result<()> {
     engine.isready().await?;
 
     let job = Go::new().moves(&["f2f3"]).depth(25);
-    let cmd = engine.prepare(job);
+    engine.start(job).await?;
 
     let timer = time::sleep(Duration::from_secs(1));
     tokio::pin!(timer);
 
-    engine.tx.send(cmd).await?;
     loop {
         select! {
-            Some(line) = engine.rx.recv() => match search(&line) {
-                Some(Search::Info(info)) => {
-                    tracing::info!(?info);
-                },
-                Some(Search::BestMove(best)) => {
+            search = engine.recv() => match search? {
+                Search::Info(info) => tracing::info!(?info),
+                Search::BestMove(best) => {
                     tracing::info!(?best);
                     break;
-                },
-                None => continue,
+                }
             },
             _ = &mut timer => {
-                engine.stop().await?;
+                if let Some((info, best)) = engine.stop().await? {
+                    tracing::info!(?info, ?best, "stopped");
+                }
                 break;
             },
         }
@@ -0,0 +1,40 @@
+use anyhow::Result;
+
+use uci::{engine::Engine, pgn};
+
+/// Crazyhouse needs an engine with `UCI_Variant`, like Fairy-Stockfish.
+const PGN: &[u8] = br#"[Event "Casual"]
+[Variant "Crazyhouse"]
+
+1. e4 d5 2. exd5 Qxd5 3. Nc3 Qd8 4. P@d4 *
+"#;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let pgn = match std::env::args().nth(1) {
+        Some(path) => std::fs::read(path)?,
+        None => PGN.to_vec(),
+    };
+    let games = pgn::read_games(&pgn[..])?;
+
+    let mut engine = Engine::builder("fairy-stockfish")
+        .option("Threads", 8)
+        .option("UCI_ShowWDL", true)
+        .spawn()
+        .await?;
+
+    for game in &games {
+        for (ply, m) in game.moves.iter().enumerate() {
+            let (info, best) = engine.go(game.go(ply).depth(12)).await?;
+            println!(
+                "{:>3}. {:<8} {:?} (best {})",
+                ply / 2 + 1,
+                m.san,
+                info.score,
+                best.best
+            );
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,149 @@
+//! Infinite analysis that follows the position on a board, see [`Analysis`].
+
+use std::time::Duration;
+
+use anyhow::Result;
+use tokio::{
+    select,
+    sync::{mpsc, watch},
+    task::JoinHandle,
+    time,
+};
+use tracing::debug;
+
+use crate::{
+    backend::ChessEngine,
+    engine::{Engine, Go},
+    search::Search,
+};
+
+/// A search update, tagged with the position it belongs to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Update {
+    /// The value returned by [`Analysis::set`] for the position being searched.
+    pub generation: u64,
+    /// Usually an `Info`. A `BestMove` only arrives when the search ends on its own, e.g. on a
+    /// finished game or when the job has limits.
+    pub search: Search,
+}
+
+/// Keeps an engine analysing the latest position it was given.
+///
+/// Every call to [`Analysis::set`] stops the current search, waits until the positions stop
+/// changing for the debounce period, and starts over on the last one. The old search is drained
+/// up to its `bestmove` first, so no update from a previous position is ever delivered after a
+/// newer one started.
+///
+/// Any [`ChessEngine`] can be used, a UCI [`Engine`] by default.
+///
+/// ```no_run
+/// # async fn example() -> anyhow::Result<()> {
+/// use std::time::Duration;
+/// use uci::{analysis::Analysis, engine::{Engine, Go}};
+///
+/// let engine = Engine::builder("stockfish").spawn().await?;
+/// let mut analysis = Analysis::start(engine, Duration::from_millis(150));
+///
+/// analysis.set(Go::default().moves(&["e2e4"]));
+/// while let Some(update) = analysis.recv().await {
+///     println!("{}", update.search);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Analysis<E = Engine> {
+    position: watch::Sender<(u64, Option<Go>)>,
+    updates: mpsc::Receiver<Update>,
+    task: JoinHandle<Result<E>>,
+}
+
+impl<E: ChessEngine> Analysis<E> {
+    pub fn start(engine: E, debounce: Duration) -> Self {
+        let (position, rx) = watch::channel((0, None));
+        let (tx, updates) = mpsc::channel(64);
+        let task = tokio::spawn(run(engine, rx, tx, debounce));
+        Self {
+            position,
+            updates,
+            task,
+        }
+    }
+
+    /// Switch to a new position, usually with `Go::default()` to search without limits.
+    ///
+    /// Returns the generation the updates for this position will be tagged with.
+    pub fn set(&self, job: Go) -> u64 {
+        let mut generation = 0;
+        self.position.send_modify(|(n, position)| {
+            *n += 1;
+            *position = Some(job);
+            generation = *n;
+        });
+        generation
+    }
+
+    /// The next update, or `None` once the analysis ended.
+    pub async fn recv(&mut self) -> Option<Update> {
+        self.updates.recv().await
+    }
+
+    /// Stop analysing and hand the engine back, idle.
+    pub async fn finish(self) -> Result<E> {
+        drop(self.position);
+        drop(self.updates);
+        self.task.await?
+    }
+}
+
+async fn run<E: ChessEngine>(
+    mut engine: E,
+    mut position: watch::Receiver<(u64, Option<Go>)>,
+    updates: mpsc::Sender<Update>,
+    debounce: Duration,
+) -> Result<E> {
+    let mut searching: Option<u64> = None;
+
+    loop {
+        select! {
+            changed = position.changed() => {
+                if searching.take().is_some() {
+                    engine.stop().await?;
+                }
+                if changed.is_err() {
+                    break;
+                }
+
+                // Let rapid changes settle before restarting.
+                loop {
+                    match time::timeout(debounce, position.changed()).await {
+                        Ok(Ok(())) => continue,
+                        Ok(Err(_)) => return Ok(engine),
+                        Err(_) => break,
+                    }
+                }
+
+                let (generation, job) = position.borrow_and_update().clone();
+                if let Some(job) = job {
+                    debug!(generation, "analysing");
+                    engine.start(job).await?;
+                    searching = Some(generation);
+                }
+            }
+            search = engine.recv(), if searching.is_some() => {
+                let search = search?;
+                let generation = searching.unwrap_or_default();
+                if let Search::BestMove(_) = search {
+                    searching = None;
+                }
+                if updates.send(Update { generation, search }).await.is_err() {
+                    if searching.is_some() {
+                        engine.stop().await?;
+                    }
+                    break;
+                }
+            }
+        }
+    }
+
+    Ok(engine)
+}
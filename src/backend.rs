@@ -0,0 +1,149 @@
+//! What higher level code needs from an engine, whatever protocol it speaks.
+
+use std::future::Future;
+
+use anyhow::{Context, Result};
+
+use crate::{
+    engine::{Engine, Go},
+    search::{BestMove, Info, Search},
+    xboard::XBoard,
+};
+
+/// The operations shared by the UCI [`Engine`], scripted mocks included, and [`XBoard`].
+///
+/// Code written against this trait, like [`Analysis`] or [`EnginePool`], works with any of
+/// them.
+///
+/// ```no_run
+/// use uci::{backend::ChessEngine, engine::Go};
+///
+/// async fn best<E: ChessEngine>(engine: &mut E, moves: &[&str]) -> anyhow::Result<String> {
+///     engine.new_game().await?;
+///     let (_, best) = engine.go(Go::new().moves(moves)).await?;
+///     Ok(best.best)
+/// }
+/// ```
+///
+/// [`Analysis`]: crate::analysis::Analysis
+/// [`EnginePool`]: crate::pool::EnginePool
+pub trait ChessEngine: Send + 'static {
+    /// Run the protocol handshake, usually done already when connecting.
+    fn handshake(&mut self) -> impl Future<Output = Result<()>> + Send;
+
+    /// The name the engine gave during the handshake.
+    fn name(&self) -> Option<&str>;
+
+    fn set_option(&mut self, name: &str, value: &str) -> impl Future<Output = Result<()>> + Send;
+
+    /// Tell the engine the next searches belong to another game.
+    fn new_game(&mut self) -> impl Future<Output = Result<()>> + Send;
+
+    /// Send the position and start searching, follow up with [`ChessEngine::recv`].
+    fn start(&mut self, job: Go) -> impl Future<Output = Result<()>> + Send;
+
+    /// Wait for the next `Info` or the final `BestMove` of the current search.
+    fn recv(&mut self) -> impl Future<Output = Result<Search>> + Send;
+
+    /// Interrupt the current search and return its result, `None` without one.
+    fn stop(&mut self) -> impl Future<Output = Result<Option<(Info, BestMove)>>> + Send;
+
+    fn quit(self) -> impl Future<Output = Result<()>> + Send
+    where
+        Self: Sized;
+
+    /// Search until the engine plays a move, returning it with the last `Info`.
+    fn go(&mut self, job: Go) -> impl Future<Output = Result<(Info, BestMove)>> + Send {
+        async move {
+            self.start(job).await?;
+            let mut last = None;
+            loop {
+                match self.recv().await? {
+                    Search::Info(info) => last = Some(info),
+                    Search::BestMove(best) => {
+                        return Ok((last.context("no info before bestmove")?, best));
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl ChessEngine for Engine {
+    /// Send `uci` and wait until the engine is ready.
+    async fn handshake(&mut self) -> Result<()> {
+        self.uci().await?;
+        self.isready().await
+    }
+
+    fn name(&self) -> Option<&str> {
+        Engine::name(self)
+    }
+
+    async fn set_option(&mut self, name: &str, value: &str) -> Result<()> {
+        self.opts(&[(name, value)]).await
+    }
+
+    async fn new_game(&mut self) -> Result<()> {
+        Engine::new_game(self).await
+    }
+
+    async fn start(&mut self, job: Go) -> Result<()> {
+        Engine::start(self, job).await
+    }
+
+    async fn recv(&mut self) -> Result<Search> {
+        Engine::recv(self).await
+    }
+
+    async fn stop(&mut self) -> Result<Option<(Info, BestMove)>> {
+        Engine::stop(self).await
+    }
+
+    async fn quit(self) -> Result<()> {
+        Engine::quit(self).await
+    }
+
+    async fn go(&mut self, job: Go) -> Result<(Info, BestMove)> {
+        Engine::go(self, job).await
+    }
+}
+
+impl ChessEngine for XBoard {
+    /// Negotiate the features again.
+    async fn handshake(&mut self) -> Result<()> {
+        XBoard::handshake(self).await
+    }
+
+    fn name(&self) -> Option<&str> {
+        XBoard::name(self)
+    }
+
+    async fn set_option(&mut self, name: &str, value: &str) -> Result<()> {
+        XBoard::set_option(self, name, value).await
+    }
+
+    async fn new_game(&mut self) -> Result<()> {
+        XBoard::new_game(self).await
+    }
+
+    async fn start(&mut self, job: Go) -> Result<()> {
+        XBoard::start(self, job).await
+    }
+
+    async fn recv(&mut self) -> Result<Search> {
+        XBoard::recv(self).await
+    }
+
+    async fn stop(&mut self) -> Result<Option<(Info, BestMove)>> {
+        XBoard::stop(self).await
+    }
+
+    async fn quit(self) -> Result<()> {
+        XBoard::quit(self).await
+    }
+
+    async fn go(&mut self, job: Go) -> Result<(Info, BestMove)> {
+        XBoard::go(self, job).await
+    }
+}
@@ -0,0 +1,91 @@
+//! Presents itself as a UCI engine and forwards everything to a real one, logging both ways.
+//!
+//! ```text
+//! uci-proxy [--log <file>] [--record <file.jsonl>] [--force <name>=<value>]...
+//!           [--cap <name>=<max>]... [--rename <name>] (--profile <name> | <engine> [args...])
+//! ```
+
+use std::fs::File;
+
+use anyhow::{Context, Result, bail};
+
+use uci::{
+    engine::EngineBuilder,
+    profile::Profiles,
+    proxy::{self, Log, Rewrite},
+};
+
+const USAGE: &str = "usage: uci-proxy [--log <file>] [--record <file>] [--force <name>=<value>]... \
+                     [--cap <name>=<max>]... [--rename <name>] (--profile <name> | <engine> [args...])";
+
+fn split(arg: &str) -> Result<(&str, &str)> {
+    arg.split_once('=')
+        .with_context(|| format!("expected <name>=<value>, got {arg}"))
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let mut argv = std::env::args().skip(1);
+    let mut rewrite = Rewrite::new();
+    let mut log: Option<String> = None;
+    let mut record: Option<String> = None;
+    let mut builder: Option<EngineBuilder> = None;
+
+    while let Some(arg) = argv.next() {
+        let mut value = || {
+            argv.next()
+                .with_context(|| format!("missing value for {arg}"))
+        };
+        match arg.as_str() {
+            "--log" => log = Some(value()?),
+            "--record" => record = Some(value()?),
+            "--force" => {
+                let value = value()?;
+                let (name, value) = split(&value)?;
+                rewrite = rewrite.force(name, value);
+            }
+            "--cap" => {
+                let value = value()?;
+                let (name, max) = split(&value)?;
+                rewrite = rewrite.cap(name, max.parse()?);
+            }
+            "--rename" => rewrite = rewrite.rename(value()?),
+            "--profile" => {
+                let profiles = Profiles::find()?;
+                let profile = profiles.get(&value()?)?;
+                // Without our own handshake, the profile options are applied by forcing them.
+                for (name, value) in &profile.options {
+                    rewrite = rewrite.force(name, value);
+                }
+                builder = Some(profile.builder());
+            }
+            "--help" | "-h" => bail!(USAGE),
+            path if !path.starts_with('-') => {
+                builder = Some(EngineBuilder::new(path).args(argv.by_ref()));
+            }
+            other => bail!("unknown argument: {other}\n{USAGE}"),
+        }
+    }
+
+    let mut builder = builder.context(USAGE)?.handshake(false);
+    if let Some(path) = record {
+        builder = builder.record(path);
+    }
+    let engine = builder.spawn().await?;
+
+    let log = match log {
+        Some(path) => {
+            Log::new(File::create(&path).with_context(|| format!("failed to create {path}"))?)
+        }
+        None => Log::new(std::io::stderr()),
+    };
+
+    proxy::run(
+        engine,
+        rewrite,
+        log,
+        tokio::io::stdin(),
+        tokio::io::stdout(),
+    )
+    .await
+}
@@ -0,0 +1,215 @@
+//! A persistent cache of search results, see [`Cache`].
+
+use std::{collections::BTreeSet, path::Path};
+
+use anyhow::{Context, Result, bail};
+use redb::{Database, ReadableTable, TableDefinition, TableError};
+use tracing::{debug, warn};
+
+use crate::{
+    engine::{Engine, Go},
+    search::{BestMove, Info},
+    variant,
+};
+
+const ANALYSIS: TableDefinition<&str, &str> = TableDefinition::new("analysis");
+
+/// Options that change how fast an engine searches but not what it finds.
+const IGNORED: &[&str] = &["Threads", "Hash", "Ponder", "Debug Log File"];
+
+/// Search results stored on disk, in front of [`Engine::go`].
+///
+/// Entries are keyed by the engine name, the options it was given, the position reached after
+/// the moves (without the move counters) and the search limit. A search to some depth is
+/// answered by any stored result at least as deep; other limits must match exactly and infinite
+/// searches are never cached.
+///
+/// ```no_run
+/// # async fn example() -> anyhow::Result<()> {
+/// use uci::{cache::Cache, engine::{Engine, Go}};
+///
+/// let cache = Cache::open("analysis.redb")?;
+/// let mut engine = Engine::builder("stockfish").spawn().await?;
+///
+/// let (info, best) = cache.go(&mut engine, Go::new().depth(24)).await?;
+/// // Served from disk, even after a restart.
+/// let (info, best) = cache.go(&mut engine, Go::new().depth(18)).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Cache {
+    db: Database,
+    ignored: BTreeSet<String>,
+}
+
+impl Cache {
+    /// Open the cache at `path`, creating it if needed.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let db = Database::create(path)
+            .with_context(|| format!("failed to open cache {}", path.display()))?;
+        Ok(Self {
+            db,
+            ignored: IGNORED.iter().map(|name| name.to_lowercase()).collect(),
+        })
+    }
+
+    /// Leave an option out of the key, on top of `Threads`, `Hash` and `Ponder`.
+    pub fn ignore_option(mut self, name: impl AsRef<str>) -> Self {
+        self.ignored.insert(name.as_ref().to_lowercase());
+        self
+    }
+
+    /// Search with the engine unless a stored result answers the request already.
+    ///
+    /// Searches that cannot be keyed, e.g. before the `uci` handshake gave the engine a name,
+    /// go straight to the engine.
+    pub async fn go(&self, engine: &mut Engine, job: Go) -> Result<(Info, BestMove)> {
+        if job.is_infinite() {
+            return engine.go(job).await;
+        }
+        let key = match self.key(engine, &job) {
+            Ok(key) => key,
+            Err(e) => {
+                warn!(cause = %e, "searching without the cache");
+                return engine.go(job).await;
+            }
+        };
+        if let Some(hit) = self.lookup(&key, &job)? {
+            debug!(best = hit.1.best, "cache hit");
+            return Ok(hit);
+        }
+
+        let (info, best) = engine.go(job.clone()).await?;
+        self.store(&key, &job, &info, &best)?;
+        Ok((info, best))
+    }
+
+    pub fn get(&self, engine: &Engine, job: &Go) -> Result<Option<(Info, BestMove)>> {
+        self.lookup(&self.key(engine, job)?, job)
+    }
+
+    /// Store a result, replacing any shallower one for the same position.
+    pub fn insert(&self, engine: &Engine, job: &Go, info: &Info, best: &BestMove) -> Result<()> {
+        self.store(&self.key(engine, job)?, job, info, best)
+    }
+
+    fn lookup(&self, key: &str, job: &Go) -> Result<Option<(Info, BestMove)>> {
+        let tx = self.db.begin_read()?;
+        let table = match tx.open_table(ANALYSIS) {
+            Ok(table) => table,
+            Err(TableError::TableDoesNotExist(_)) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let Some(value) = table.get(key)? else {
+            return Ok(None);
+        };
+
+        let entry = Entry::parse(value.value())?;
+        // Only a result searched at least as deep answers a depth request.
+        if let Some(wanted) = job.depth
+            && entry.depth.is_none_or(|stored| stored < wanted)
+        {
+            return Ok(None);
+        }
+        Ok(Some((entry.info, entry.best)))
+    }
+
+    fn store(&self, key: &str, job: &Go, info: &Info, best: &BestMove) -> Result<()> {
+        let entry = Entry {
+            depth: job.depth,
+            info: info.clone(),
+            best: best.clone(),
+        };
+
+        let tx = self.db.begin_write()?;
+        {
+            let mut table = tx.open_table(ANALYSIS)?;
+            let stored = table
+                .get(key)?
+                .map(|value| Entry::parse(value.value()))
+                .transpose()?;
+            if let Some(Entry {
+                depth: Some(stored),
+                ..
+            }) = stored
+                && entry.depth.is_some_and(|depth| depth < stored)
+            {
+                return Ok(());
+            }
+            table.insert(key, entry.to_string().as_str())?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn key(&self, engine: &Engine, job: &Go) -> Result<String> {
+        let name = engine
+            .name()
+            .context("engine has no name, run the uci handshake first")?;
+        let options = engine
+            .options()
+            .iter()
+            .filter(|(name, _)| !self.ignored.contains(&name.to_lowercase()))
+            .map(|(name, value)| format!("{}={value}", name.to_lowercase()))
+            .collect::<Vec<_>>()
+            .join(";");
+
+        // Depth-only searches share a key so deeper results can answer shallower requests.
+        let limit = match (job.depth, job.movetime, job.mate) {
+            (Some(_), None, None) => "depth".to_string(),
+            (None, None, None) => bail!("infinite searches are not cached"),
+            (depth, movetime, mate) => [
+                ("depth", depth.map(u64::from)),
+                ("movetime", movetime),
+                ("mate", mate.map(u64::from)),
+            ]
+            .into_iter()
+            .filter_map(|(name, value)| Some(format!("{name} {}", value?)))
+            .collect::<Vec<_>>()
+            .join(" "),
+        };
+
+        let limit = match job.searchmoves.as_slice() {
+            [] => limit,
+            moves => format!("{limit} searchmoves {}", moves.join(" ")),
+        };
+
+        let variant = job.variant.unwrap_or(engine.variant());
+        let position = variant::normalize(variant, job.fen.as_deref(), &job.moves)?;
+        Ok(format!("{name}\n{options}\n{position}\n{limit}"))
+    }
+}
+
+/// A stored result, as the requested depth followed by the final `info` and `bestmove` lines.
+struct Entry {
+    depth: Option<u32>,
+    info: Info,
+    best: BestMove,
+}
+
+impl Entry {
+    fn parse(s: &str) -> Result<Self> {
+        let mut lines = s.lines();
+        let mut next = || lines.next().context("truncated cache entry");
+        let depth = match next()? {
+            "-" => None,
+            depth => Some(depth.parse()?),
+        };
+        Ok(Self {
+            depth,
+            info: next()?.parse()?,
+            best: next()?.parse()?,
+        })
+    }
+}
+
+impl std::fmt::Display for Entry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.depth {
+            Some(depth) => writeln!(f, "{depth}")?,
+            None => writeln!(f, "-")?,
+        }
+        write!(f, "{}\n{}", self.info, self.best)
+    }
+}
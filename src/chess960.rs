@@ -0,0 +1,195 @@
+//! Chess960 (Fischer Random) positions and castling notation.
+//!
+//! In `UCI_Chess960` mode engines castle by moving the king onto its own rook (`e1h1`) and
+//! expect castling rights that name the rook files when the outermost rook is not the one
+//! that castles. Engines spawned with [`EngineBuilder::chess960`] get their positions
+//! translated automatically.
+//!
+//! [`EngineBuilder::chess960`]: crate::engine::EngineBuilder::chess960
+
+use anyhow::{Context, Result, bail};
+use shakmaty::{CastlingMode, Chess, Color, EnPassantMode, Position, fen::Fen, uci::UciMove};
+
+/// How castling rights are written in a FEN.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FenStyle {
+    /// `KQkq` when the outermost rooks castle, the rook files otherwise.
+    #[default]
+    XFen,
+    /// Always the rook files, e.g. `HAha`.
+    Shredder,
+}
+
+/// The number of the classical start position.
+pub const STANDARD: u16 = 518;
+
+/// The back rank of start position `n`, from the a-file, in Scharnagl's numbering.
+fn back_rank(n: u16) -> Result<[char; 8]> {
+    if n >= 960 {
+        bail!("no Chess960 start position {n}, expected 0 to 959");
+    }
+    let mut rank = [' '; 8];
+
+    let (n, light) = (n / 4, n % 4);
+    rank[usize::from(light) * 2 + 1] = 'B';
+    let (n, dark) = (n / 4, n % 4);
+    rank[usize::from(dark) * 2] = 'B';
+    let (n, queen) = (n / 6, n % 6);
+
+    let mut place = |skip: u16, piece: char| {
+        let file = (0..8)
+            .filter(|&file| rank[file] == ' ')
+            .nth(usize::from(skip))
+            .unwrap();
+        rank[file] = piece;
+    };
+    place(queen, 'Q');
+
+    // The knights go on two of the five files left, the second one counted after the first.
+    let (first, second) = [
+        (0, 1),
+        (0, 2),
+        (0, 3),
+        (0, 4),
+        (1, 2),
+        (1, 3),
+        (1, 4),
+        (2, 3),
+        (2, 4),
+        (3, 4),
+    ][usize::from(n)];
+    place(first, 'N');
+    place(second - 1, 'N');
+
+    for piece in ['R', 'K', 'R'] {
+        place(0, piece);
+    }
+    Ok(rank)
+}
+
+/// Start position `n`, with the same arrangement for both sides.
+pub fn start_position(n: u16) -> Result<Chess> {
+    double_start_position(n, n)
+}
+
+/// A double Chess960 start position, with a different arrangement for each side.
+pub fn double_start_position(white: u16, black: u16) -> Result<Chess> {
+    let white: String = back_rank(white)?.iter().collect();
+    let black = back_rank(black)?.iter().collect::<String>().to_lowercase();
+    position(&format!(
+        "{black}/pppppppp/8/8/8/8/PPPPPPPP/{white} w KQkq - 0 1"
+    ))
+}
+
+pub fn start_fen(n: u16, style: FenStyle) -> Result<String> {
+    Ok(fen(&start_position(n)?, style))
+}
+
+/// All 960 start position numbers in an order that only depends on `seed`.
+///
+/// Take the first few for a match, or pair them up for double Chess960.
+pub fn shuffled(seed: u64) -> Vec<u16> {
+    // SplitMix64, enough to spread the positions without pulling in a random number crate.
+    let mut state = seed;
+    let mut next = move || {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    };
+
+    let mut numbers: Vec<u16> = (0..960).collect();
+    for i in (1..numbers.len()).rev() {
+        let j = (next() % (i as u64 + 1)) as usize;
+        numbers.swap(i, j);
+    }
+    numbers
+}
+
+/// Parse a FEN in Chess960 mode, accepting both X-FEN and Shredder-FEN castling rights.
+pub fn position(fen: &str) -> Result<Chess> {
+    let fen = Fen::from_ascii(fen.as_bytes()).with_context(|| format!("invalid fen: {fen}"))?;
+    Ok(fen.into_position(CastlingMode::Chess960)?)
+}
+
+pub fn fen(pos: &Chess, style: FenStyle) -> String {
+    let fen = Fen::from_position(pos.clone(), EnPassantMode::Legal).to_string();
+    if style == FenStyle::XFen {
+        return fen;
+    }
+
+    let mut castling = String::new();
+    for color in Color::ALL {
+        let rooks = pos.castles().castling_rights() & color.backrank();
+        for rook in rooks.into_iter().rev() {
+            let file = rook.file().char();
+            castling.push(color.fold_wb(file.to_ascii_uppercase(), file));
+        }
+    }
+    if castling.is_empty() {
+        castling.push('-');
+    }
+
+    let mut fields: Vec<&str> = fen.split(' ').collect();
+    fields[2] = &castling;
+    fields.join(" ")
+}
+
+/// Write a move in Chess960 notation, where castling is king takes rook.
+///
+/// Both notations are accepted, standard castling only with the king on its original square.
+pub fn to_chess960(pos: &Chess, mv: &str) -> Result<String> {
+    let m = UciMove::from_ascii(mv.as_bytes())?.to_move(pos)?;
+    Ok(UciMove::from_move(&m, CastlingMode::Chess960).to_string())
+}
+
+/// Write a move in standard notation, where castling moves the king two squares.
+///
+/// Only meaningful for positions where that is unambiguous, like classical chess played by an
+/// engine in Chess960 mode.
+pub fn to_standard(pos: &Chess, mv: &str) -> Result<String> {
+    let m = UciMove::from_ascii(mv.as_bytes())?.to_move(pos)?;
+    Ok(UciMove::from_move(&m, CastlingMode::Standard).to_string())
+}
+
+/// Convert a line of moves, like `Info::pv`, played from `pos` to standard notation.
+pub fn line_to_standard(pos: &Chess, moves: &[String]) -> Result<Vec<String>> {
+    let mut pos = pos.clone();
+    moves
+        .iter()
+        .map(|mv| {
+            let m = UciMove::from_ascii(mv.as_bytes())?.to_move(&pos)?;
+            pos.play_unchecked(&m);
+            Ok(UciMove::from_move(&m, CastlingMode::Standard).to_string())
+        })
+        .collect()
+}
+
+/// The position, moves and searchmoves of a search, as an engine in Chess960 mode expects
+/// them.
+pub(crate) fn translate(
+    fen: Option<&str>,
+    moves: &[String],
+    searchmoves: &[String],
+    style: FenStyle,
+) -> Result<(Option<String>, Vec<String>, Vec<String>)> {
+    let start = fen.map(position).transpose()?;
+    let mut pos = start.clone().unwrap_or_default();
+    let moves = moves
+        .iter()
+        .map(|mv| {
+            let m = UciMove::from_ascii(mv.as_bytes())?.to_move(&pos)?;
+            pos.play_unchecked(&m);
+            Ok(UciMove::from_move(&m, CastlingMode::Chess960).to_string())
+        })
+        .collect::<Result<_>>()?;
+    let searchmoves = searchmoves
+        .iter()
+        .map(|mv| {
+            let m = UciMove::from_ascii(mv.as_bytes())?.to_move(&pos)?;
+            Ok(UciMove::from_move(&m, CastlingMode::Chess960).to_string())
+        })
+        .collect::<Result<_>>()?;
+    Ok((start.map(|pos| self::fen(&pos, style)), moves, searchmoves))
+}
@@ -1,18 +1,48 @@
-use std::{fmt::Write, path::Path, process::Stdio};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, VecDeque},
+    ffi::OsString,
+    fmt::Write,
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, anyhow, bail};
+use shakmaty::{
+    EnPassantMode,
+    fen::Fen,
+    variant::{Variant, VariantPosition},
+};
 use tokio::{
-    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
-    process::{Child, ChildStdin, ChildStdout, Command},
-    sync::mpsc,
+    io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
+    net::{TcpStream, ToSocketAddrs},
+    process::{Child, ChildStderr, Command},
+    sync::{Notify, mpsc},
+    time,
 };
-use tracing::{debug, error, trace};
+use tracing::{debug, error, trace, warn};
 
-use crate::search::{BestMove, Info, Search};
+use crate::{
+    chess960::{self, FenStyle},
+    option::UciOption,
+    search::{BestMove, Info, Search},
+    transcript::{Direction, Recorder},
+    variant,
+};
 
-async fn writer(mut stdin: ChildStdin, mut rx: mpsc::Receiver<String>) -> Result<()> {
+async fn writer(
+    mut stdin: impl AsyncWrite + Unpin,
+    mut rx: mpsc::Receiver<String>,
+    recorder: Option<Recorder>,
+) -> Result<()> {
     while let Some(mut cmd) = rx.recv().await {
         trace!("-> {cmd}");
+        if let Some(recorder) = &recorder {
+            cmd.lines()
+                .for_each(|line| recorder.record(Direction::Send, line));
+        }
         cmd.push('\n');
         stdin.write_all(cmd.as_bytes()).await?;
         stdin.flush().await?;
@@ -20,26 +50,48 @@ async fn writer(mut stdin: ChildStdin, mut rx: mpsc::Receiver<String>) -> Result
     Ok(())
 }
 
-async fn reader(stdout: ChildStdout, tx: mpsc::Sender<String>) -> Result<()> {
+async fn reader(
+    stdout: impl AsyncRead + Unpin,
+    tx: mpsc::Sender<String>,
+    recorder: Option<Recorder>,
+) -> Result<()> {
     let mut reader = BufReader::new(stdout).lines();
     while let Some(line) = reader.next_line().await? {
         trace!("<- {line}");
+        if let Some(recorder) = &recorder {
+            recorder.record(Direction::Recv, &line);
+        }
         tx.send(line).await?;
     }
     Ok(())
 }
 
-#[derive(Debug, Default)]
+/// A search request: the position and the limits.
+///
+/// With the `serde` feature, serializes as `{ "fen": null, "moves": ["e2e4"], "depth": 20, "movetime": null,
+/// "mate": null, "searchmoves": [] }`.
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct Go {
-    fen: Option<String>,
-    moves: Vec<String>,
-    depth: u32,
+    pub(crate) fen: Option<String>,
+    pub(crate) moves: Vec<String>,
+    pub(crate) depth: Option<u32>,
+    pub(crate) movetime: Option<u64>,
+    pub(crate) mate: Option<u32>,
+    pub(crate) searchmoves: Vec<String>,
+    /// Not serialized, the variant of a position is known from the session.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) variant: Option<Variant>,
 }
 
 impl Go {
+    /// A search from the start position limited to depth 10.
+    ///
+    /// Use `Go::default()` to start without any limit and add your own.
     pub fn new() -> Self {
         Self {
-            depth: 10,
+            depth: Some(10),
             ..Default::default()
         }
     }
@@ -57,85 +109,482 @@ impl Go {
     }
 
     pub fn depth(mut self, depth: u32) -> Self {
-        self.depth = depth;
+        self.depth = Some(depth);
+        self
+    }
+
+    /// Search a variant position, switching the engine to the variant first if needed.
+    pub fn variant(mut self, variant: Variant) -> Self {
+        self.variant = Some(variant);
         self
     }
 
-    pub async fn execute(self, engine: &Engine) -> Result<()> {
-        // engine.go(self).await
-        todo!()
+    /// Search from `pos`, in its variant.
+    pub fn position(self, pos: &VariantPosition) -> Self {
+        let fen = Fen::from_position(pos.clone(), EnPassantMode::Legal);
+        self.fen(fen.to_string()).variant(pos.variant())
+    }
+
+    /// Search for exactly `ms` milliseconds.
+    pub fn movetime(mut self, ms: u64) -> Self {
+        self.movetime = Some(ms);
+        self
+    }
+
+    /// Search for a mate in at most `moves` moves, see [`crate::mate`].
+    pub fn mate(mut self, moves: u32) -> Self {
+        self.mate = Some(moves);
+        self
+    }
+
+    /// Only consider these moves from the position searched.
+    pub fn searchmoves(mut self, moves: &[impl AsRef<str>]) -> Self {
+        for mv in moves {
+            self.searchmoves.push(mv.as_ref().into());
+        }
+        self
+    }
+
+    /// Whether the search has no limit and only ends on `stop`.
+    pub fn is_infinite(&self) -> bool {
+        self.depth.is_none() && self.movetime.is_none() && self.mate.is_none()
+    }
+
+    pub async fn execute(self, engine: &mut Engine) -> Result<(Info, BestMove)> {
+        engine.go(self).await
+    }
+}
+
+/// How many lines of stderr are kept around to explain failures.
+const STDERR_LINES: usize = 64;
+
+/// How long an engine gets to exit after `quit` before it is killed.
+pub(crate) const QUIT_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Default, Clone)]
+struct Stderr {
+    lines: Arc<Mutex<VecDeque<String>>>,
+    /// Set once the engine closed its stderr, usually because it exited.
+    closed: Arc<Notify>,
+}
+
+async fn stderr(stderr: ChildStderr, log: Stderr) -> Result<()> {
+    let mut reader = BufReader::new(stderr).lines();
+    while let Some(line) = reader.next_line().await? {
+        trace!("!! {line}");
+        let mut lines = log.lines.lock().unwrap();
+        if lines.len() == STDERR_LINES {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+    log.closed.notify_one();
+    Ok(())
+}
+
+/// Configures how an engine process is spawned and set up.
+///
+/// ```no_run
+/// # async fn example() -> anyhow::Result<()> {
+/// use uci::engine::Engine;
+///
+/// let engine = Engine::builder("lc0")
+///     .arg("--backend=cuda")
+///     .current_dir("/opt/lc0")
+///     .option("WeightsFile", "t2.pb.gz")
+///     .spawn()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct EngineBuilder {
+    path: PathBuf,
+    args: Vec<OsString>,
+    env: Vec<(OsString, OsString)>,
+    dir: Option<PathBuf>,
+    stderr: bool,
+    options: Vec<(String, String)>,
+    handshake: bool,
+    #[cfg(feature = "serde")]
+    transcript: Option<PathBuf>,
+    chess960: Option<FenStyle>,
+}
+
+impl EngineBuilder {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            args: Vec::new(),
+            env: Vec::new(),
+            dir: None,
+            stderr: true,
+            options: Vec::new(),
+            handshake: true,
+            #[cfg(feature = "serde")]
+            transcript: None,
+            chess960: None,
+        }
+    }
+
+    pub fn arg(mut self, arg: impl Into<OsString>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    pub fn args(mut self, args: impl IntoIterator<Item = impl Into<OsString>>) -> Self {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn env(mut self, key: impl Into<OsString>, value: impl Into<OsString>) -> Self {
+        self.env.push((key.into(), value.into()));
+        self
+    }
+
+    /// The working directory of the engine, needed by engines that load files relatively.
+    pub fn current_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.dir = Some(dir.into());
+        self
+    }
+
+    /// Keep the last lines the engine writes to stderr and attach them to errors. On by default.
+    pub fn capture_stderr(mut self, capture: bool) -> Self {
+        self.stderr = capture;
+        self
+    }
+
+    /// An option set right after the handshake.
+    pub fn option(mut self, name: impl Into<String>, value: impl ToString) -> Self {
+        self.options.push((name.into(), value.to_string()));
+        self
+    }
+
+    /// Send `uci`, the initial options and `isready` once spawned. On by default.
+    ///
+    /// Options are only sent during the handshake, so [`EngineBuilder::spawn`] fails when
+    /// there are any and the handshake is off.
+    pub fn handshake(mut self, handshake: bool) -> Self {
+        self.handshake = handshake;
+        self
+    }
+
+    /// Record every line exchanged with the engine to a JSONL transcript.
+    #[cfg(feature = "serde")]
+    pub fn record(mut self, path: impl Into<PathBuf>) -> Self {
+        self.transcript = Some(path.into());
+        self
+    }
+
+    /// Play Chess960: set `UCI_Chess960` and translate positions to what the engine expects.
+    ///
+    /// Castling moves can then be given in either notation, see [`crate::chess960`].
+    pub fn chess960(mut self, style: FenStyle) -> Self {
+        self.chess960 = Some(style);
+        self.option("UCI_Chess960", true)
+    }
+
+    /// Spawn the process without talking to it yet.
+    fn launch(&self) -> Result<Engine> {
+        let mut command = Command::new(&self.path);
+        command
+            .args(&self.args)
+            .envs(self.env.iter().map(|(k, v)| (k, v)))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(if self.stderr {
+                Stdio::piped()
+            } else {
+                Stdio::inherit()
+            });
+        if let Some(dir) = &self.dir {
+            command.current_dir(dir);
+        }
+
+        let mut child = command
+            .spawn()
+            .with_context(|| format!("failed to spawn {}", self.path.display()))?;
+
+        let stdin = child.stdin.take().context("failed to open stdin")?;
+        let stdout = child.stdout.take().context("failed to open stdout")?;
+
+        #[cfg(feature = "serde")]
+        let recorder = self.transcript.as_ref().map(Recorder::create).transpose()?;
+        #[cfg(not(feature = "serde"))]
+        let recorder = None;
+        let mut engine = Engine::connect(stdout, stdin, recorder);
+        if let Some(output) = child.stderr.take() {
+            let log = Stderr::default();
+            engine.stderr = Some(log.clone());
+            tokio::spawn(async move {
+                if let Err(e) = stderr(output, log).await {
+                    error!(cause = %e, "stderr error");
+                }
+            });
+        }
+        engine.child = Some(child);
+
+        Ok(engine)
+    }
+
+    /// The initial options, leaving none to send during the handshake.
+    pub(crate) fn take_options(&mut self) -> Vec<(String, String)> {
+        std::mem::take(&mut self.options)
+    }
+
+    pub async fn spawn(self) -> Result<Engine> {
+        if !self.handshake && !self.options.is_empty() {
+            bail!("options are sent during the handshake, which is turned off");
+        }
+        let mut engine = self.launch()?;
+        if self.handshake {
+            engine.uci().await?;
+            if !self.options.is_empty() {
+                engine.opts(&self.options).await?;
+            }
+            engine.isready().await?;
+        }
+        engine.chess960 = self.chess960;
+        Ok(engine)
     }
 }
 
 pub struct Engine {
-    _child: Child,
+    child: Option<Child>,
+    stderr: Option<Stderr>,
+    name: Option<String>,
+    options: BTreeMap<String, String>,
+    /// Set from [`Engine::start`] until the `bestmove` is received.
+    searching: bool,
+    /// The last main line `Info` of the current search.
+    last: Option<Info>,
+    chess960: Option<FenStyle>,
+    /// The options advertised during [`Engine::uci`].
+    supported: Vec<UciOption>,
+    variant: Variant,
     pub tx: mpsc::Sender<String>,
     pub rx: mpsc::Receiver<String>,
 }
 
 impl Engine {
+    /// Spawn an engine process without any arguments or handshake.
     pub fn new(path: impl AsRef<Path>) -> Result<Self> {
-        let mut child = Command::new(path.as_ref())
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .spawn()?;
+        EngineBuilder::new(path.as_ref())
+            .capture_stderr(false)
+            .launch()
+    }
 
-        let stdin = child.stdin.take().context("failed to open stdin")?;
-        let stdout = child.stdout.take().context("failed to open stdout")?;
+    pub fn builder(path: impl Into<PathBuf>) -> EngineBuilder {
+        EngineBuilder::new(path)
+    }
+
+    /// Talk to an engine over any pair of async streams instead of a child process.
+    ///
+    /// This is how in-process engines are plugged in, e.g. through [`tokio::io::duplex`].
+    pub fn from_io(
+        stdout: impl AsyncRead + Unpin + Send + 'static,
+        stdin: impl AsyncWrite + Unpin + Send + 'static,
+    ) -> Self {
+        Self::connect(stdout, stdin, None)
+    }
 
+    /// Like [`Engine::from_io`] but records the session to a transcript.
+    pub fn from_io_recorded(
+        stdout: impl AsyncRead + Unpin + Send + 'static,
+        stdin: impl AsyncWrite + Unpin + Send + 'static,
+        recorder: Recorder,
+    ) -> Self {
+        Self::connect(stdout, stdin, Some(recorder))
+    }
+
+    fn connect(
+        stdout: impl AsyncRead + Unpin + Send + 'static,
+        stdin: impl AsyncWrite + Unpin + Send + 'static,
+        recorder: Option<Recorder>,
+    ) -> Self {
         let (input_tx, input_rx) = mpsc::channel(32);
+        let input_recorder = recorder.clone();
         tokio::spawn(async move {
-            if let Err(e) = writer(stdin, input_rx).await {
+            if let Err(e) = writer(stdin, input_rx, input_recorder).await {
                 error!(cause = %e, "writer error");
             }
         });
 
         let (output_tx, output_rx) = mpsc::channel(32);
         tokio::spawn(async move {
-            if let Err(e) = reader(stdout, output_tx).await {
+            if let Err(e) = reader(stdout, output_tx, recorder).await {
                 error!(cause = %e, "reader error");
             }
         });
 
-        Ok(Self {
-            _child: child,
+        Self {
+            child: None,
+            stderr: None,
+            name: None,
+            options: BTreeMap::new(),
+            searching: false,
+            last: None,
+            chess960: None,
+            supported: Vec::new(),
+            variant: Variant::Chess,
             tx: input_tx,
             rx: output_rx,
-        })
+        }
+    }
+
+    /// Connect to an engine served over TCP.
+    pub async fn tcp(addr: impl ToSocketAddrs) -> Result<Self> {
+        let stream = TcpStream::connect(addr).await?;
+        let (reader, writer) = stream.into_split();
+        Ok(Self::from_io(reader, writer))
+    }
+
+    /// Connect to an engine served over a Unix domain socket.
+    #[cfg(unix)]
+    pub async fn unix(path: impl AsRef<Path>) -> Result<Self> {
+        let stream = tokio::net::UnixStream::connect(path).await?;
+        let (reader, writer) = stream.into_split();
+        Ok(Self::from_io(reader, writer))
     }
 
     #[tracing::instrument(level = "debug", skip(self))]
-    pub async fn wait(&mut self, keyword: &str) {
+    pub async fn wait(&mut self, keyword: &str) -> Result<()> {
         while let Some(line) = self.rx.recv().await {
             if line == keyword {
-                break;
+                return Ok(());
             }
         }
+        Err(self.closed(&format!("while waiting for {keyword}")).await)
     }
 
+    /// The last lines the engine wrote to stderr, if captured.
+    pub fn stderr(&self) -> Vec<String> {
+        self.stderr
+            .as_ref()
+            .map(|log| log.lines.lock().unwrap().iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub(crate) async fn closed(&self, during: &str) -> anyhow::Error {
+        if let Some(log) = &self.stderr {
+            // Stdout and stderr close independently, give the last words a chance to arrive.
+            _ = time::timeout(Duration::from_millis(100), log.closed.notified()).await;
+        }
+
+        let stderr = self.stderr();
+        if stderr.is_empty() {
+            anyhow!("engine closed the connection {during}")
+        } else {
+            anyhow!(
+                "engine closed the connection {during}, stderr:\n{}",
+                stderr.join("\n")
+            )
+        }
+    }
+
+    /// Send `uci` and wait for `uciok`, remembering the `id name` and options of the engine.
     pub async fn uci(&mut self) -> Result<()> {
         self.tx.send("uci".into()).await?;
-        self.wait("uciok").await;
-        Ok(())
+        self.supported.clear();
+        while let Some(line) = self.rx.recv().await {
+            if let Some(name) = line.strip_prefix("id name ") {
+                self.name = Some(name.trim().into());
+            } else if line.starts_with("option ") {
+                match line.parse() {
+                    Ok(option) => self.supported.push(option),
+                    Err(e) => warn!(cause = %e, "skipping malformed option: {line}"),
+                }
+            } else if line == "uciok" {
+                return Ok(());
+            }
+        }
+        Err(self.closed("while waiting for uciok").await)
+    }
+
+    /// The name the engine gave during [`Engine::uci`].
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// The options the engine advertised during [`Engine::uci`].
+    pub fn supported_options(&self) -> &[UciOption] {
+        &self.supported
+    }
+
+    /// The variant set through `UCI_Variant`, chess unless changed.
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    /// The options set through [`Engine::opts`] so far.
+    pub fn options(&self) -> &BTreeMap<String, String> {
+        &self.options
     }
 
     pub async fn isready(&mut self) -> Result<()> {
         self.tx.send("isready".into()).await?;
-        self.wait("readyok").await;
+        self.wait("readyok").await?;
         debug!("READY");
         Ok(())
     }
 
-    pub async fn stop(&mut self) -> Result<()> {
-        self.tx.send("stop\nisready".into()).await?;
-        self.wait("readyok").await;
-        debug!("READY");
+    /// Send `ucinewgame`, for engines that keep state between searches of the same game.
+    pub async fn new_game(&mut self) -> Result<()> {
+        self.tx.send("ucinewgame".into()).await?;
+        self.isready().await
+    }
+
+    /// Send `quit` and wait for the process to exit, killing it if it takes too long.
+    pub async fn quit(mut self) -> Result<()> {
+        self.tx.send("quit".into()).await?;
+        self.reap(QUIT_TIMEOUT).await
+    }
+
+    /// Wait for the process to exit after `quit`, killing it if it takes longer than `timeout`.
+    pub(crate) async fn reap(&mut self, timeout: Duration) -> Result<()> {
+        if let Some(mut child) = self.child.take()
+            && time::timeout(timeout, child.wait()).await.is_err()
+        {
+            warn!("engine did not exit on quit, killing it");
+            child.kill().await?;
+        }
         Ok(())
     }
 
-    pub async fn opts<O: std::fmt::Display>(&self, options: &[(O, O)]) -> Result<()> {
+    /// Interrupt the current search and return its `bestmove` with the last main line `Info`.
+    ///
+    /// The search may have ended already, with the `bestmove` still waiting to be read, in
+    /// which case that one is returned. Without a search in progress this returns `None`.
+    /// Either way, nothing from the search is left in [`Engine::rx`] afterwards.
+    ///
+    /// The `Info` is the default one if the engine did not report any.
+    pub async fn stop(&mut self) -> Result<Option<(Info, BestMove)>> {
+        let mut stopped = None;
+        if self.searching {
+            self.tx.send("stop".into()).await?;
+            loop {
+                if let Search::BestMove(best) = self.recv().await? {
+                    stopped = Some((self.last.take().unwrap_or_default(), best));
+                    break;
+                }
+            }
+        }
+
+        // Flush anything the engine wrote after the bestmove.
+        self.isready().await?;
+        Ok(stopped)
+    }
+
+    pub async fn opts<O: std::fmt::Display>(&mut self, options: &[(O, O)]) -> Result<()> {
         let cmd = options.iter().fold(String::new(), |mut acc, (k, v)| {
             _ = writeln!(&mut acc, "setoption name {k} value {v}");
+            let (k, v) = (k.to_string(), v.to_string());
+            if k.eq_ignore_ascii_case("UCI_Chess960") {
+                self.chess960 = (v == "true").then(|| self.chess960.unwrap_or_default());
+            } else if k.eq_ignore_ascii_case("UCI_Variant") {
+                self.variant = variant::from_uci_name(&v).unwrap_or(Variant::Chess);
+            }
+            self.options.insert(k, v);
             acc
         });
         self.tx.send(cmd).await?;
@@ -143,51 +592,185 @@ impl Engine {
     }
 
     pub fn prepare(&self, job: Go) -> String {
+        let untranslated = || (job.fen.clone(), job.moves.clone(), job.searchmoves.clone());
+        let (fen, moves, searchmoves) = match self.chess960 {
+            Some(style) => {
+                chess960::translate(job.fen.as_deref(), &job.moves, &job.searchmoves, style)
+                    .unwrap_or_else(|e| {
+                        warn!(cause = %e, "sending the position untranslated");
+                        untranslated()
+                    })
+            }
+            None => untranslated(),
+        };
+
         let mut cmd = "position".to_string();
-        match &job.fen {
+        match &fen {
             None => _ = write!(&mut cmd, " startpos"),
             Some(fen) => _ = write!(&mut cmd, " fen {fen}"),
         };
-        if !job.moves.is_empty() {
-            _ = write!(&mut cmd, " moves {}", job.moves.join(" "));
+        if !moves.is_empty() {
+            _ = write!(&mut cmd, " moves {}", moves.join(" "));
         }
         cmd.push('\n');
 
-        _ = writeln!(&mut cmd, "go depth {}", job.depth);
+        cmd.push_str("go");
+        if let Some(depth) = job.depth {
+            _ = write!(&mut cmd, " depth {depth}");
+        }
+        if let Some(mate) = job.mate {
+            _ = write!(&mut cmd, " mate {mate}");
+        }
+        if let Some(movetime) = job.movetime {
+            _ = write!(&mut cmd, " movetime {movetime}");
+        }
+        if job.is_infinite() {
+            cmd.push_str(" infinite");
+        }
+        // Last, as Stockfish reads every token after it as a move.
+        if !searchmoves.is_empty() {
+            _ = write!(&mut cmd, " searchmoves {}", searchmoves.join(" "));
+        }
+        cmd.push('\n');
 
         cmd
     }
 
-    pub async fn go(&mut self, job: Go) -> Result<(Info, BestMove)> {
+    /// Send the position and start searching without waiting for the result.
+    ///
+    /// Follow up with [`Engine::recv`] until a [`Search::BestMove`] arrives.
+    pub async fn start(&mut self, job: Go) -> Result<()> {
+        if let Some(variant) = job.variant
+            && variant != self.variant
+        {
+            self.set_variant(variant).await?;
+        }
         let cmd = self.prepare(job);
         self.tx.send(cmd).await?;
+        self.searching = true;
+        self.last = None;
+        Ok(())
+    }
 
-        let mut last: Option<Info> = None;
-        let mut best: Option<BestMove> = None;
+    /// Wait for the next search update, skipping any other engine output.
+    pub async fn recv(&mut self) -> Result<Search> {
+        self.recv_with(|_| {}).await
+    }
 
+    /// Like [`Engine::recv`] but hands the lines that are not search updates to `f`, e.g. the
+    /// `info string` ones.
+    pub async fn recv_with(&mut self, mut f: impl FnMut(&str)) -> Result<Search> {
         while let Some(line) = self.rx.recv().await {
-            match search(&line) {
-                Some(Search::Info(info)) => last = Some(info),
-                Some(Search::BestMove(b)) => {
-                    best = Some(b);
-                    break;
-                }
-                None => continue,
+            let Some(search) = search(&line) else {
+                f(&line);
+                continue;
             };
+            match &search {
+                Search::Info(info) if info.multipv.unwrap_or(1) <= 1 => {
+                    self.last = Some(info.clone())
+                }
+                Search::BestMove(_) => self.searching = false,
+                _ => {}
+            }
+            return Ok(search);
         }
+        Err(self.closed("during search").await)
+    }
 
-        Ok((last.unwrap(), best.unwrap()))
+    pub async fn go(&mut self, job: Go) -> Result<(Info, BestMove)> {
+        self.go_with(job, |_| {}).await
+    }
+
+    /// Search the best `lines` moves with `MultiPV`, returning the last `Info` of each line, best
+    /// first. There are fewer lines when the position has fewer legal moves.
+    ///
+    /// `MultiPV` is only sent when it differs from the value set last, and is set back to that
+    /// value once the search is over, or to 1 if it was never set. [`Engine::options`] are left
+    /// as they were, so the search does not change the keys of [`crate::cache::Cache`].
+    pub async fn go_multi(&mut self, job: Go, lines: u32) -> Result<(Vec<Info>, BestMove)> {
+        let previous = self.options.get("MultiPV").cloned();
+        let changed = previous.as_deref().unwrap_or("1") != lines.to_string();
+        if changed {
+            self.opts(&[("MultiPV".to_string(), lines.to_string())])
+                .await?;
+        }
+
+        let result = self.search_lines(job).await;
+        if changed {
+            let value = previous.clone().unwrap_or_else(|| "1".into());
+            self.opts(&[("MultiPV".to_string(), value)]).await?;
+            if previous.is_none() {
+                self.options.remove("MultiPV");
+            }
+        }
+        result
+    }
+
+    async fn search_lines(&mut self, job: Go) -> Result<(Vec<Info>, BestMove)> {
+        self.start(job).await?;
+
+        let mut infos: Vec<Info> = Vec::new();
+        loop {
+            match self.recv().await? {
+                Search::Info(info) => {
+                    let line = info.multipv.unwrap_or(1).max(1) as usize - 1;
+                    match line.cmp(&infos.len()) {
+                        Ordering::Less => infos[line] = info,
+                        Ordering::Equal => infos.push(info),
+                        Ordering::Greater => warn!(line, "skipping info of a line out of order"),
+                    }
+                }
+                Search::BestMove(best) => {
+                    if infos.is_empty() {
+                        bail!("no info before bestmove");
+                    }
+                    return Ok((infos, best));
+                }
+            }
+        }
+    }
+
+    /// Like [`Engine::go`] but calls `f` on every `Info` as it arrives.
+    ///
+    /// The `Info` returned is the last one of the best line, when `MultiPV` is above 1.
+    pub async fn go_with(&mut self, job: Go, mut f: impl FnMut(&Info)) -> Result<(Info, BestMove)> {
+        self.start(job).await?;
+
+        let mut last: Option<Info> = None;
+        loop {
+            match self.recv().await? {
+                Search::Info(info) => {
+                    f(&info);
+                    if info.multipv.unwrap_or(1) <= 1 {
+                        last = Some(info);
+                    }
+                }
+                Search::BestMove(best) => {
+                    let info = last.context("no info before bestmove")?;
+                    return Ok((info, best));
+                }
+            }
+        }
     }
 }
 
+/// Parse a line of engine output into a search update.
+///
+/// Lines that are not search updates, or that fail to parse, yield `None`.
 pub fn search(line: &str) -> Option<Search> {
-    if line.starts_with("info depth") {
-        let info = line.parse::<Info>().unwrap();
-        return Some(Search::Info(info));
-    }
-    if line.starts_with("bestmove") {
-        let best = line.parse::<BestMove>().unwrap();
-        return Some(Search::BestMove(best));
+    let search = if line.starts_with("info depth") {
+        line.parse::<Info>().map(Search::Info)
+    } else if line.starts_with("bestmove") {
+        line.parse::<BestMove>().map(Search::BestMove)
+    } else {
+        return None;
+    };
+
+    match search {
+        Ok(search) => Some(search),
+        Err(e) => {
+            warn!(%line, cause = %e, "malformed search line");
+            None
+        }
     }
-    None
 }
@@ -0,0 +1,292 @@
+use std::{collections::BTreeMap, io::Write, str::FromStr};
+
+use anyhow::{Context, Result, bail};
+use shakmaty::{CastlingMode, Chess, fen::Fen, san::San};
+
+use crate::{
+    engine::{Engine, Go},
+    search::Info,
+};
+
+/// A single line of an EPD test suite.
+///
+/// Only the opcodes relevant for engine testing are kept: `bm`, `am`, `id`, `hmvc`, `fmvn`
+/// and the comments `c0` to `c9`. Everything else is ignored.
+#[derive(Debug, Default, Clone)]
+pub struct Epd {
+    /// The first four fields of a FEN: placement, side to move, castling and en passant.
+    pub epd: String,
+    pub id: Option<String>,
+    /// The best moves in SAN as written in the suite.
+    pub bm: Vec<String>,
+    /// The moves to avoid in SAN as written in the suite.
+    pub am: Vec<String>,
+    pub comments: BTreeMap<u8, String>,
+    pub halfmoves: u32,
+    pub fullmoves: u32,
+}
+
+impl Epd {
+    /// The full FEN, using the `hmvc` and `fmvn` opcodes for the move counters.
+    pub fn fen(&self) -> String {
+        format!("{} {} {}", self.epd, self.halfmoves, self.fullmoves.max(1))
+    }
+
+    pub fn position(&self) -> Result<Chess> {
+        let fen: Fen = self.fen().parse()?;
+        Ok(fen.into_position(CastlingMode::Standard)?)
+    }
+
+    /// The `bm` moves converted to UCI notation.
+    pub fn best_moves(&self) -> Result<Vec<String>> {
+        to_uci(&self.position()?, &self.bm)
+    }
+
+    /// The `am` moves converted to UCI notation.
+    pub fn avoid_moves(&self) -> Result<Vec<String>> {
+        to_uci(&self.position()?, &self.am)
+    }
+}
+
+fn to_uci(pos: &Chess, moves: &[String]) -> Result<Vec<String>> {
+    moves
+        .iter()
+        .map(|san| {
+            let mv = San::from_str(san.trim_end_matches(['+', '#', '!', '?']))
+                .with_context(|| format!("invalid san: {san}"))?
+                .to_move(pos)
+                .with_context(|| format!("illegal move: {san}"))?;
+            Ok(mv.to_uci(CastlingMode::Standard).to_string())
+        })
+        .collect()
+}
+
+/// Split the operations of an EPD line into `(opcode, operands)` pairs.
+///
+/// Operations are terminated by `;` and string operands are enclosed in double quotes,
+/// which may themselves contain `;`.
+fn operations(s: &str) -> Result<Vec<(String, Vec<String>)>> {
+    let mut ops = Vec::new();
+    let mut chars = s.chars().peekable();
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            break;
+        }
+
+        let mut opcode = String::new();
+        while let Some(c) = chars.next_if(|c| !c.is_whitespace() && *c != ';') {
+            opcode.push(c);
+        }
+
+        let mut operands = Vec::new();
+        loop {
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+            match chars.next() {
+                None => bail!("unterminated operation: {opcode}"),
+                Some(';') => break,
+                Some('"') => {
+                    let mut operand = String::new();
+                    loop {
+                        match chars.next() {
+                            None => bail!("unterminated string in operation: {opcode}"),
+                            Some('"') => break,
+                            Some(c) => operand.push(c),
+                        }
+                    }
+                    operands.push(operand);
+                }
+                Some(c) => {
+                    let mut operand = c.to_string();
+                    while let Some(c) = chars.next_if(|c| !c.is_whitespace() && *c != ';') {
+                        operand.push(c);
+                    }
+                    operands.push(operand);
+                }
+            }
+        }
+
+        ops.push((opcode, operands));
+    }
+
+    Ok(ops)
+}
+
+fn parse_epd(line: &str) -> Result<Epd> {
+    let mut rest = line.trim();
+
+    let mut epd = Epd {
+        fullmoves: 1,
+        ..Default::default()
+    };
+    for name in ["placement", "side to move", "castling", "en passant"] {
+        if rest.is_empty() {
+            bail!("no {name}");
+        }
+        // Fields may be separated by any run of whitespace.
+        let (field, tail) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        if !epd.epd.is_empty() {
+            epd.epd.push(' ');
+        }
+        epd.epd.push_str(field);
+        rest = tail.trim_start();
+    }
+
+    for (opcode, mut operands) in operations(rest)? {
+        match opcode.as_str() {
+            "bm" => epd.bm = operands,
+            "am" => epd.am = operands,
+            "id" => epd.id = operands.pop(),
+            "hmvc" => epd.halfmoves = operands.first().context("no hmvc")?.parse()?,
+            "fmvn" => epd.fullmoves = operands.first().context("no fmvn")?.parse()?,
+            c if c.len() == 2 && c.starts_with('c') => {
+                if let Some(n) = c[1..].parse().ok()
+                    && let Some(comment) = operands.pop()
+                {
+                    epd.comments.insert(n, comment);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    if epd.bm.is_empty() && epd.am.is_empty() {
+        bail!("no bm or am operation");
+    }
+
+    Ok(epd)
+}
+
+impl FromStr for Epd {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        parse_epd(s)
+    }
+}
+
+/// Parse a whole suite, skipping blank lines and `#` comments.
+pub fn parse_suite(s: &str) -> Result<Vec<Epd>> {
+    s.lines()
+        .enumerate()
+        .filter(|(_, line)| {
+            let line = line.trim();
+            !line.is_empty() && !line.starts_with('#')
+        })
+        .map(|(n, line)| line.parse().with_context(|| format!("line {}", n + 1)))
+        .collect()
+}
+
+/// The outcome of running a single EPD position.
+#[derive(Debug)]
+pub struct Solution {
+    pub id: Option<String>,
+    pub fen: String,
+    pub best: String,
+    pub solved: bool,
+    /// The depth at which the correct move appeared in the PV and stayed there.
+    pub depth: Option<u32>,
+    /// The time in milliseconds at which the correct move appeared in the PV and stayed there.
+    pub time: Option<u64>,
+    /// The last info reported by the engine.
+    pub info: Info,
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    pub solutions: Vec<Solution>,
+}
+
+impl Report {
+    pub fn solved(&self) -> usize {
+        self.solutions.iter().filter(|s| s.solved).count()
+    }
+
+    pub fn total(&self) -> usize {
+        self.solutions.len()
+    }
+
+    /// Write the report as CSV, one row per position.
+    pub fn write_csv(&self, mut w: impl Write) -> Result<()> {
+        writeln!(
+            w,
+            "id,fen,best,solved,depth,time,final_depth,final_time,nodes"
+        )?;
+        for s in &self.solutions {
+            writeln!(
+                w,
+                "{},{},{},{},{},{},{},{},{}",
+                csv_field(s.id.as_deref().unwrap_or_default()),
+                csv_field(&s.fen),
+                s.best,
+                s.solved,
+                s.depth.map(|d| d.to_string()).unwrap_or_default(),
+                s.time.map(|t| t.to_string()).unwrap_or_default(),
+                s.info.depth,
+                s.info.time.map(|t| t.to_string()).unwrap_or_default(),
+                s.info.nodes.map(|n| n.to_string()).unwrap_or_default(),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.into()
+    }
+}
+
+/// Run every position of a suite through the engine.
+///
+/// `limit` is used as a template for each search, its position is replaced by the EPD one.
+pub async fn run(engine: &mut Engine, suite: &[Epd], limit: &Go) -> Result<Report> {
+    let mut report = Report::default();
+    for epd in suite {
+        report.solutions.push(solve(engine, epd, limit).await?);
+    }
+    Ok(report)
+}
+
+pub async fn solve(engine: &mut Engine, epd: &Epd, limit: &Go) -> Result<Solution> {
+    let best = epd.best_moves()?;
+    let avoid = epd.avoid_moves()?;
+    let correct = |mv: &str| {
+        (best.is_empty() || best.iter().any(|b| b == mv)) && !avoid.iter().any(|a| a == mv)
+    };
+
+    let mut found = None;
+    let job = limit.clone().fen(epd.fen());
+    let (info, bestmove) = engine
+        .go_with(job, |info| {
+            if info.multipv.unwrap_or(1) > 1 {
+                return;
+            }
+            let Some(mv) = info.pv.first() else { return };
+            if !correct(mv) {
+                found = None;
+            } else if found.is_none() {
+                found = Some((info.depth, info.time));
+            }
+        })
+        .await?;
+
+    let solved = correct(&bestmove.best);
+    let (depth, time) = match found {
+        Some((depth, time)) if solved => (Some(depth), time),
+        _ => (None, None),
+    };
+
+    Ok(Solution {
+        id: epd.id.clone(),
+        fen: epd.fen(),
+        best: bestmove.best,
+        solved,
+        depth,
+        time,
+        info,
+    })
+}
@@ -0,0 +1,235 @@
+//! A local analysis server over HTTP and WebSocket, see `uci serve`.
+//!
+//! - `GET /status` returns `{ "engines": 2, "live": 2, "idle": 1 }`, `live` being the engines
+//!   that have not died.
+//! - `POST /analyse` takes a [`Go`] as JSON, which must have a depth or movetime, and answers
+//!   `{ "info": { ... }, "bestmove": { ... } }` once the search is over.
+//! - `GET /ws` upgrades to a WebSocket. Send `{ "go": { ... } }` to start a search, limits
+//!   optional, and `"stop"` to cancel it. Every update is sent back as a [`Search`] in JSON,
+//!   ending with the `bestmove`.
+//!
+//! Positions and moves are checked before they reach an engine, an illegal one is a 400 or an
+//! error on the WebSocket. Failures are reported as `{ "error": "..." }`.
+
+use anyhow::{Result, anyhow};
+use axum::{
+    Json, Router,
+    extract::{
+        State,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use serde::{Deserialize, Serialize};
+use shakmaty::{
+    EnPassantMode, Position,
+    fen::Fen,
+    uci::UciMove,
+    variant::{Variant, VariantPosition},
+};
+use tokio::{net::TcpListener, select};
+use tracing::{debug, info};
+
+use crate::{
+    engine::Go,
+    pool::{EnginePool, PooledEngine},
+    search::{BestMove, Info, Search},
+    variant,
+};
+
+#[derive(Debug, Serialize)]
+struct Status {
+    engines: usize,
+    live: usize,
+    idle: usize,
+}
+
+/// The response to `POST /analyse`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Analysis {
+    pub info: Info,
+    pub bestmove: BestMove,
+}
+
+/// A message from a WebSocket client.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Request {
+    Go(Go),
+    Stop,
+}
+
+struct Error(StatusCode, anyhow::Error);
+
+impl From<anyhow::Error> for Error {
+    fn from(e: anyhow::Error) -> Self {
+        Self(StatusCode::INTERNAL_SERVER_ERROR, e)
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        (self.0, error(self.1)).into_response()
+    }
+}
+
+fn error(e: anyhow::Error) -> Json<serde_json::Value> {
+    Json(serde_json::json!({ "error": format!("{e:#}") }))
+}
+
+/// The job with its position and moves checked, and written back the way shakmaty writes them.
+///
+/// Jobs come from any client and go to engines other clients share, so only a legal position
+/// and legal moves may reach them. A newline in a FEN or a move would start another command.
+fn checked(mut job: Go, variant: Variant) -> Result<Go> {
+    let start = variant::position(job.variant.unwrap_or(variant), job.fen.as_deref())?;
+    let mode = start.castles().mode();
+    let uci = |pos: &mut VariantPosition, mv: &str| -> Result<String> {
+        let m = variant::line(pos, &[mv.into()])?.remove(0);
+        pos.play_unchecked(&m);
+        Ok(UciMove::from_move(&m, mode).to_string())
+    };
+
+    let mut pos = start.clone();
+    job.moves = job
+        .moves
+        .iter()
+        .map(|mv| uci(&mut pos, mv))
+        .collect::<Result<_>>()?;
+    job.searchmoves = job
+        .searchmoves
+        .iter()
+        .map(|mv| uci(&mut pos.clone(), mv))
+        .collect::<Result<_>>()?;
+    if job.fen.is_some() {
+        job.fen = Some(Fen::from_position(start, EnPassantMode::Legal).to_string());
+    }
+    Ok(job)
+}
+
+pub fn router(pool: EnginePool) -> Router {
+    Router::new()
+        .route("/status", get(status))
+        .route("/analyse", post(analyse))
+        .route("/ws", get(ws))
+        .with_state(pool)
+}
+
+/// Serve the pool on `listener` until the process is killed.
+pub async fn serve(listener: TcpListener, pool: EnginePool) -> Result<()> {
+    info!("listening on http://{}", listener.local_addr()?);
+    axum::serve(listener, router(pool)).await?;
+    Ok(())
+}
+
+async fn status(State(pool): State<EnginePool>) -> Json<Status> {
+    Json(Status {
+        engines: pool.size(),
+        live: pool.live(),
+        idle: pool.idle(),
+    })
+}
+
+async fn analyse(
+    State(pool): State<EnginePool>,
+    Json(job): Json<Go>,
+) -> Result<Json<Analysis>, Error> {
+    if job.is_infinite() {
+        return Err(Error(
+            StatusCode::BAD_REQUEST,
+            anyhow!("a depth or movetime is required"),
+        ));
+    }
+    let mut engine = pool.get().await?;
+    let job = checked(job, engine.variant()).map_err(|e| Error(StatusCode::BAD_REQUEST, e))?;
+    let (info, bestmove) = engine.go(job).await?;
+    Ok(Json(Analysis { info, bestmove }))
+}
+
+async fn ws(State(pool): State<EnginePool>, upgrade: WebSocketUpgrade) -> Response {
+    upgrade.on_upgrade(|socket| async move {
+        if let Err(e) = session(socket, pool).await {
+            debug!(cause = %e, "websocket closed");
+        }
+    })
+}
+
+async fn send(socket: &mut WebSocket, value: &impl Serialize) -> Result<()> {
+    let text = serde_json::to_string(value)?;
+    socket.send(Message::Text(text.into())).await?;
+    Ok(())
+}
+
+/// Run searches for a single WebSocket client, one at a time.
+///
+/// A `go` waits for an idle engine without blocking the socket, so a `stop` or a disconnect
+/// while every engine is busy drops the request. The engine goes back to the pool after each
+/// `bestmove`, or when the client disconnects.
+async fn session(mut socket: WebSocket, pool: EnginePool) -> Result<()> {
+    let mut pending: Option<Go> = None;
+    let mut searching: Option<PooledEngine> = None;
+
+    loop {
+        select! {
+            msg = socket.recv() => {
+                let Some(msg) = msg else { break };
+                let Message::Text(text) = msg? else { continue };
+
+                match serde_json::from_str(&text) {
+                    Ok(Request::Go(_)) if pending.is_some() || searching.is_some() => {
+                        send(&mut socket, &error(anyhow!("already searching")).0).await?;
+                    }
+                    Ok(Request::Go(job)) => pending = Some(job),
+                    Ok(Request::Stop) => {
+                        pending = None;
+                        if let Some(mut engine) = searching.take() {
+                            match engine.stop().await {
+                                Ok(Some((info, best))) => {
+                                    if info != Info::default() {
+                                        send(&mut socket, &Search::Info(info)).await?;
+                                    }
+                                    send(&mut socket, &Search::BestMove(best)).await?;
+                                }
+                                Ok(None) => {}
+                                Err(e) => send(&mut socket, &error(e).0).await?,
+                            }
+                        }
+                    }
+                    Err(e) => send(&mut socket, &error(e.into()).0).await?,
+                }
+            }
+            engine = pool.get(), if pending.is_some() => {
+                let job = pending.take().unwrap();
+                let started = async {
+                    let mut engine = engine?;
+                    let job = checked(job, engine.variant())?;
+                    engine.start(job).await?;
+                    Ok::<_, anyhow::Error>(engine)
+                }
+                .await;
+                match started {
+                    Ok(engine) => searching = Some(engine),
+                    Err(e) => send(&mut socket, &error(e).0).await?,
+                }
+            }
+            update = async { searching.as_mut().unwrap().recv().await }, if searching.is_some() => {
+                match update {
+                    Ok(search) => {
+                        send(&mut socket, &search).await?;
+                        if let Search::BestMove(_) = search {
+                            searching = None;
+                        }
+                    }
+                    Err(e) => {
+                        searching = None;
+                        send(&mut socket, &error(e).0).await?;
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
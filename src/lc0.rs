@@ -0,0 +1,198 @@
+//! The per-move statistics Lc0 prints as `info string` lines with `VerboseMoveStats`.
+//!
+//! ```text
+//! info string d2d4  (293 ) N:     421 (+ 3) (P: 22.87%) (WL:  0.04150) (D: 0.415) (M: 143.2) (Q:  0.04150) (U: 0.01851) (S:  0.06001) (V:  0.0366)
+//! info string node  ( 20 ) N:    1000 (+ 8) (P: 100.00%) (WL:  0.03020) (D: 0.421) (M: 141.0) (Q:  0.03020) (V:  0.0291)
+//! ```
+//!
+//! Fields an Lc0 version does not print, or prints as `-.----`, are `None`.
+
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+
+use crate::{
+    engine::{Engine, Go},
+    search::{BestMove, Info, Search},
+};
+
+/// The statistics of a root move, or of the root itself for the `node` line.
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct MoveStats {
+    /// The move in UCI notation, or `node`.
+    pub mv: String,
+    /// The index of the move in the policy output of the network.
+    pub index: u32,
+    /// `N`, the number of visits.
+    pub visits: u64,
+    /// Visits still being evaluated when the line was printed.
+    pub in_flight: u64,
+    /// `P`, the policy prior in percent.
+    pub policy: f64,
+    /// `WL`, the expected win minus loss, from -1 to 1.
+    pub wl: Option<f64>,
+    /// `D`, the draw probability.
+    pub draw: Option<f64>,
+    /// `M`, the expected number of moves left.
+    pub moves_left: Option<f64>,
+    /// `Q`, the average value of the subtree used by the search.
+    pub q: Option<f64>,
+    /// `U`, the exploration term.
+    pub uncertainty: Option<f64>,
+    /// `S`, the score the move was selected on, `Q + U` and some tweaks.
+    pub selection: Option<f64>,
+    /// `V`, the value the network gave the position after the move.
+    pub value: Option<f64>,
+    /// Marked `(T)`, the move ends the game.
+    pub terminal: bool,
+}
+
+impl MoveStats {
+    /// The win, draw and loss probabilities, from `WL` and `D`.
+    pub fn wdl(&self) -> Option<(f64, f64, f64)> {
+        let (wl, d) = (self.wl?, self.draw?);
+        Some(((1.0 + wl - d) / 2.0, d, (1.0 - wl - d) / 2.0))
+    }
+}
+
+impl FromStr for MoveStats {
+    type Err = anyhow::Error;
+
+    /// Parse a statistics line, with or without its `info string` prefix.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let line = s.strip_prefix("info string ").unwrap_or(s).trim();
+        let (mv, rest) = line.split_once(' ').context("no move")?;
+        let (index, rest) = rest
+            .trim_start()
+            .strip_prefix('(')
+            .and_then(|rest| rest.split_once(')'))
+            .context("no policy index")?;
+        let rest = rest.trim_start().strip_prefix("N:").context("no visits")?;
+        let (visits, mut rest) = rest.trim_start().split_once(' ').unwrap_or((rest, ""));
+
+        let mut stats = MoveStats {
+            mv: mv.into(),
+            index: index.trim().parse()?,
+            visits: visits.trim().parse()?,
+            ..Default::default()
+        };
+
+        while let Some(start) = rest.find('(') {
+            let (group, tail) = rest[start + 1..]
+                .split_once(')')
+                .context("unclosed group")?;
+            rest = tail;
+            let Some((key, value)) = group.split_once(':') else {
+                let group = group.trim();
+                if group == "T" {
+                    stats.terminal = true;
+                } else if let Some(n) = group.strip_prefix('+') {
+                    stats.in_flight = n.trim().parse()?;
+                }
+                continue;
+            };
+
+            let value = value.trim();
+            let number = value.trim_end_matches('%').parse::<f64>().ok();
+            match key.trim() {
+                "P" => stats.policy = number.context("no policy")?,
+                "WL" => stats.wl = number,
+                "D" => stats.draw = number,
+                "M" => stats.moves_left = number,
+                "Q" => stats.q = number,
+                "U" => stats.uncertainty = number,
+                "S" => stats.selection = number,
+                "V" => stats.value = number,
+                // Older versions print `Q+U`, and newer ones may add more.
+                _ => (),
+            }
+        }
+
+        Ok(stats)
+    }
+}
+
+/// The statistics of all root moves at the end of a search.
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Stats {
+    /// In the order Lc0 prints them, fewest visits first.
+    pub moves: Vec<MoveStats>,
+    /// The totals of the root, printed after the moves.
+    pub node: Option<MoveStats>,
+}
+
+impl Stats {
+    /// Add a line, starting over when a new set of statistics begins.
+    pub fn push(&mut self, stats: MoveStats) {
+        if stats.mv == "node" {
+            self.node = Some(stats);
+            return;
+        }
+        if self.node.take().is_some() {
+            self.moves.clear();
+        }
+        self.moves.push(stats);
+    }
+
+    pub fn get(&self, mv: &str) -> Option<&MoveStats> {
+        self.moves.iter().find(|stats| stats.mv == mv)
+    }
+
+    /// The most visited move, the one Lc0 plays.
+    pub fn most_visited(&self) -> Option<&MoveStats> {
+        self.moves.iter().max_by_key(|stats| stats.visits)
+    }
+
+    /// The share of the visits that went to each move, next to its policy prior, both from 0
+    /// to 1. This is how far the search moved away from the network.
+    pub fn visits_and_priors(&self) -> Vec<(&str, f64, f64)> {
+        let total: u64 = self.moves.iter().map(|stats| stats.visits).sum();
+        self.moves
+            .iter()
+            .map(|stats| {
+                let share = match total {
+                    0 => 0.0,
+                    total => stats.visits as f64 / total as f64,
+                };
+                (stats.mv.as_str(), share, stats.policy / 100.0)
+            })
+            .collect()
+    }
+}
+
+impl Engine {
+    /// Like [`Engine::go`], also collecting the statistics Lc0 prints with `VerboseMoveStats`.
+    ///
+    /// Lc0 prints them before the `bestmove`, and periodically with `LogLiveStats`, the last
+    /// set is returned. Empty if the option is not set.
+    pub async fn go_stats(&mut self, job: Go) -> Result<(Info, BestMove, Stats)> {
+        self.start(job).await?;
+
+        let mut stats = Stats::default();
+        let mut last: Option<Info> = None;
+        loop {
+            let search = self
+                .recv_with(|line| {
+                    if line.starts_with("info string ")
+                        && let Ok(line) = line.parse()
+                    {
+                        stats.push(line);
+                    }
+                })
+                .await?;
+            match search {
+                // With MultiPV, only the best line is kept, like `Engine::go` does.
+                Search::Info(info) if info.multipv.unwrap_or(1) <= 1 => last = Some(info),
+                Search::Info(_) => {}
+                Search::BestMove(best) => {
+                    let info = last.context("no info before bestmove")?;
+                    return Ok((info, best, stats));
+                }
+            }
+        }
+    }
+}
@@ -1,4 +1,28 @@
+pub mod analysis;
+pub mod backend;
+pub mod cache;
+pub mod chess960;
 pub mod engine;
+pub mod epd;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod lc0;
+pub mod limit;
+pub mod mate;
+pub mod mock;
+pub mod opening;
+pub mod option;
+pub mod pgn;
+pub mod pool;
+pub mod problem;
+#[cfg(feature = "profile")]
+pub mod profile;
+pub mod proxy;
+pub mod puzzle;
 pub mod search;
+pub mod server;
+pub mod transcript;
+pub mod variant;
+pub mod xboard;
 
 pub const FEN_MATE: &str = "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4";
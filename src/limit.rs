@@ -0,0 +1,189 @@
+//! Stop conditions evaluated on the live `Info` stream, see [`Limit`].
+
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use tokio::{
+    select,
+    time::{self, Instant},
+};
+
+use crate::{
+    engine::{Engine, Go},
+    search::{BestMove, Info, Score, Search},
+};
+
+/// When to stop a search, beyond the fixed limits of [`Go`].
+///
+/// Conditions are checked on every main line `Info` and combine with [`Limit::and`] and
+/// [`Limit::or`]. Scores are only trusted when they are not a bound.
+///
+/// ```no_run
+/// # async fn example(engine: &mut uci::engine::Engine) -> anyhow::Result<()> {
+/// use std::time::Duration;
+/// use uci::{engine::Go, limit::Limit};
+///
+/// // Stop on a mate, or once the best move held for 5 depths past 10M nodes, or after 30s.
+/// let limit = Limit::mate()
+///     .or(Limit::stable(5).and(Limit::nodes(10_000_000)))
+///     .or(Limit::time(Duration::from_secs(30)));
+/// let (info, best) = engine.go_until(Go::default(), &limit).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Limit {
+    /// A mate was found, for either side.
+    Mate,
+    /// The best move stayed the same for this many depths.
+    Stable(u32),
+    /// The score, from the side to move, reached this many centipawns. Winning mates count.
+    ScoreAbove(i32),
+    Depth(u32),
+    Nodes(u64),
+    /// Wall-clock time since the search started.
+    Time(Duration),
+    And(Box<Limit>, Box<Limit>),
+    Or(Box<Limit>, Box<Limit>),
+}
+
+impl Limit {
+    pub fn mate() -> Self {
+        Self::Mate
+    }
+
+    pub fn stable(depths: u32) -> Self {
+        Self::Stable(depths)
+    }
+
+    pub fn score_above(cp: i32) -> Self {
+        Self::ScoreAbove(cp)
+    }
+
+    pub fn depth(depth: u32) -> Self {
+        Self::Depth(depth)
+    }
+
+    pub fn nodes(nodes: u64) -> Self {
+        Self::Nodes(nodes)
+    }
+
+    pub fn time(time: Duration) -> Self {
+        Self::Time(time)
+    }
+
+    /// Stop once both conditions hold.
+    pub fn and(self, other: Limit) -> Self {
+        Self::And(Box::new(self), Box::new(other))
+    }
+
+    /// Stop once either condition holds.
+    pub fn or(self, other: Limit) -> Self {
+        Self::Or(Box::new(self), Box::new(other))
+    }
+
+    fn reached(&self, progress: &Progress) -> bool {
+        let info = &progress.info;
+        let exact = info.bound.is_none();
+        match self {
+            Self::Mate => exact && matches!(info.score, Some(Score::Mate(_))),
+            Self::Stable(depths) => progress.stable >= *depths,
+            Self::ScoreAbove(cp) => {
+                exact
+                    && match info.score {
+                        Some(Score::Cp(score)) => score >= *cp,
+                        Some(Score::Mate(moves)) => moves > 0,
+                        None => false,
+                    }
+            }
+            Self::Depth(depth) => info.depth >= *depth,
+            Self::Nodes(nodes) => info.nodes.is_some_and(|n| n >= *nodes),
+            Self::Time(time) => progress.start.elapsed() >= *time,
+            Self::And(a, b) => a.reached(progress) && b.reached(progress),
+            Self::Or(a, b) => a.reached(progress) || b.reached(progress),
+        }
+    }
+
+    /// The earliest time condition still ahead, to check again when no `Info` arrives.
+    fn next_deadline(&self, elapsed: Duration) -> Option<Duration> {
+        match self {
+            Self::Time(time) if *time > elapsed => Some(*time),
+            Self::And(a, b) | Self::Or(a, b) => {
+                match (a.next_deadline(elapsed), b.next_deadline(elapsed)) {
+                    (Some(a), Some(b)) => Some(a.min(b)),
+                    (a, b) => a.or(b),
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// What the conditions are checked against.
+#[derive(Debug)]
+struct Progress {
+    start: Instant,
+    info: Info,
+    best: Option<String>,
+    /// The number of depths `best` has been the first move of the main line.
+    stable: u32,
+}
+
+impl Progress {
+    fn update(&mut self, info: Info) {
+        if let Some(mv) = info.pv.first() {
+            if self.best.as_ref() != Some(mv) {
+                self.best = Some(mv.clone());
+                self.stable = 1;
+            } else if info.depth > self.info.depth {
+                self.stable += 1;
+            }
+        }
+        self.info = info;
+    }
+}
+
+impl Engine {
+    /// Search until `limit` is reached, then stop and return the result like [`Engine::go`].
+    ///
+    /// The limits of the job still apply, use `Go::default()` to only stop on `limit`.
+    pub async fn go_until(&mut self, job: Go, limit: &Limit) -> Result<(Info, BestMove)> {
+        let mut progress = Progress {
+            start: Instant::now(),
+            info: Info::default(),
+            best: None,
+            stable: 0,
+        };
+        self.start(job).await?;
+
+        loop {
+            let deadline = limit
+                .next_deadline(progress.start.elapsed())
+                .map(|time| progress.start + time);
+            let sleep = async {
+                match deadline {
+                    Some(deadline) => time::sleep_until(deadline).await,
+                    None => std::future::pending().await,
+                }
+            };
+
+            select! {
+                search = self.recv() => match search? {
+                    Search::Info(info) if info.multipv.unwrap_or(1) <= 1 => progress.update(info),
+                    Search::Info(_) => continue,
+                    Search::BestMove(best) => {
+                        if progress.info == Info::default() {
+                            bail!("no info before bestmove");
+                        }
+                        return Ok((progress.info, best));
+                    }
+                },
+                () = sleep => {}
+            }
+
+            if limit.reached(&progress) {
+                return self.stop().await?.context("search ended without bestmove");
+            }
+        }
+    }
+}
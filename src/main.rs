@@ -1,56 +1,91 @@
-use std::time::Duration;
+use anyhow::{Context, Result, bail};
 
-use anyhow::Result;
-use tokio::{select, time};
+use uci::profile::Profiles;
 
-use uci::{
-    engine::{Engine, Go, search},
-    search::{BestMove, Info, Search},
-};
+const USAGE: &str = "usage: uci --engine <profile> [--config <file>] [--fen <fen>]
+       uci serve --engine <profile> [--config <file>] [--addr <addr>] [--engines <n>]";
+
+#[derive(Debug, Default)]
+struct Args {
+    serve: bool,
+    engine: String,
+    config: Option<String>,
+    fen: Option<String>,
+    addr: Option<String>,
+    engines: Option<usize>,
+}
+
+fn parse_args() -> Result<Args> {
+    let mut args = Args::default();
+    let mut argv = std::env::args().skip(1).peekable();
+
+    if argv.peek().is_some_and(|arg| arg == "serve") {
+        args.serve = true;
+        argv.next();
+    }
+
+    while let Some(arg) = argv.next() {
+        let mut value = || {
+            argv.next()
+                .with_context(|| format!("missing value for {arg}"))
+        };
+        match arg.as_str() {
+            "--engine" | "-e" => args.engine = value()?,
+            "--config" | "-c" => args.config = Some(value()?),
+            "--fen" => args.fen = Some(value()?),
+            "--addr" if args.serve => args.addr = Some(value()?),
+            "--engines" if args.serve => args.engines = Some(value()?.parse()?),
+            "--help" | "-h" => bail!(USAGE),
+            other => bail!("unknown argument: {other}\n{USAGE}"),
+        }
+    }
+
+    if args.engine.is_empty() {
+        bail!(USAGE);
+    }
+    Ok(args)
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
     setup_logging();
 
-    let mut engine = Engine::new("stockfish")?;
-    let options = [("Threads", "8"), ("UCI_ShowWDL", "true"), ("MultiPV", "1")];
+    let args = parse_args()?;
+    let profiles = match &args.config {
+        Some(path) => Profiles::load(path)?,
+        None => Profiles::find()?,
+    };
+    let profile = profiles.get(&args.engine)?;
 
-    engine.uci().await?;
+    if args.serve {
+        return serve(profile, &args).await;
+    }
 
-    engine.opts(&options).await?;
-    engine.isready().await?;
+    let mut engine = profile.builder().spawn().await?;
 
-    let job = Go::new().moves(&["f2f3"]).depth(25);
+    let mut job = profile.go();
+    if let Some(fen) = args.fen {
+        job = job.fen(fen);
+    }
     let (info, best) = engine.go(job).await?;
     tracing::debug!(?info, ?best);
 
-    return Ok(());
-
-    let timer = time::sleep(Duration::from_secs(1));
-    tokio::pin!(timer);
-
-    loop {
-        select! {
-            Some(line) = engine.rx.recv() => match search(&line) {
-                Some(Search::Info(info)) => {
-                    tracing::info!(?info);
-                },
-                Some(Search::BestMove(best)) => {
-                    tracing::info!(?best);
-                    break;
-                },
-                None => continue,
-            },
-            _ = &mut timer => {
-                engine.stop().await?;
-                break;
-            },
-        }
-    }
-
     Ok(())
 }
 
+#[cfg(feature = "http")]
+async fn serve(profile: &uci::profile::Profile, args: &Args) -> Result<()> {
+    let addr = args.addr.as_deref().unwrap_or("127.0.0.1:8080");
+    let pool = uci::pool::EnginePool::spawn(&profile.builder(), args.engines.unwrap_or(1)).await?;
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    uci::http::serve(listener, pool).await
+}
+
+#[cfg(not(feature = "http"))]
+async fn serve(_: &uci::profile::Profile, _: &Args) -> Result<()> {
+    bail!("uci serve requires the `http` feature")
+}
+
 fn setup_logging() {
     use tracing::level_filters::LevelFilter;
 
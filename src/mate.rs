@@ -0,0 +1,120 @@
+//! Mate searches with `go mate`, verified into a complete proof tree, see [`Engine::prove_mate`].
+
+use std::{future::Future, pin::Pin};
+
+use anyhow::{Context, Result, bail};
+use shakmaty::{Position, uci::UciMove, variant::VariantPosition};
+
+use crate::{
+    engine::{Engine, Go},
+    search::{Info, Score},
+    variant,
+};
+
+/// A verified mate: the attacking move, and the mate that follows every defence against it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Proof {
+    pub mv: String,
+    /// Every legal reply, each with the proof of the mate after it. Empty when `mv` mates.
+    pub defences: Vec<(String, Proof)>,
+}
+
+impl Proof {
+    /// The number of attacking moves in the longest line.
+    pub fn moves(&self) -> u32 {
+        1 + self
+            .defences
+            .iter()
+            .map(|(_, proof)| proof.moves())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Every line from the first move to a mate, in UCI notation.
+    pub fn lines(&self) -> Vec<Vec<String>> {
+        if self.defences.is_empty() {
+            return vec![vec![self.mv.clone()]];
+        }
+        let mut lines = Vec::new();
+        for (defence, proof) in &self.defences {
+            for line in proof.lines() {
+                let mut full = vec![self.mv.clone(), defence.clone()];
+                full.extend(line);
+                lines.push(full);
+            }
+        }
+        lines
+    }
+}
+
+type ProofFuture<'a> = Pin<Box<dyn Future<Output = Result<Option<Proof>>> + Send + 'a>>;
+
+impl Engine {
+    /// Search for a mate in at most `moves` moves with `go mate`, returning its length and
+    /// the final `Info`. `None` when the engine found no mate for the side to move.
+    ///
+    /// The limits of the job still apply, use `Go::default()` to only search for the mate.
+    pub async fn go_mate(&mut self, job: Go, moves: u32) -> Result<Option<(u32, Info)>> {
+        let (info, _) = self.go(job.mate(moves)).await?;
+        Ok(match info.score {
+            Some(Score::Mate(n)) if n > 0 && n.unsigned_abs() <= moves => {
+                Some((n.unsigned_abs(), info))
+            }
+            _ => None,
+        })
+    }
+
+    /// Find a mate in at most `moves` moves and prove it: after the mating move every legal
+    /// defence is searched in turn for the mate in one move fewer, down to the final mates.
+    ///
+    /// `None` when there is no mate, or when a claimed one does not hold against some defence.
+    /// Each defence costs a search, so keep `moves` small for positions with many replies.
+    pub async fn prove_mate(&mut self, job: Go, moves: u32) -> Result<Option<Proof>> {
+        if moves == 0 {
+            bail!("a mate takes at least one move");
+        }
+        let variant = job.variant.unwrap_or(self.variant());
+        let mut pos = variant::position(variant, job.fen.as_deref())?;
+        for m in variant::line(&pos, &job.moves)? {
+            pos.play_unchecked(&m);
+        }
+        prove(self, job, pos, moves).await
+    }
+}
+
+fn prove(engine: &mut Engine, job: Go, pos: VariantPosition, moves: u32) -> ProofFuture<'_> {
+    Box::pin(async move {
+        let Some((n, info)) = engine.go_mate(job.clone(), moves).await? else {
+            return Ok(None);
+        };
+        let mv = info.pv.first().context("mate without a move")?.clone();
+        let mut after = pos;
+        let m = UciMove::from_ascii(mv.as_bytes())?
+            .to_move(&after)
+            .with_context(|| format!("illegal move {mv}"))?;
+        after.play_unchecked(&m);
+
+        if after.is_checkmate() {
+            return Ok(Some(Proof {
+                mv,
+                defences: Vec::new(),
+            }));
+        }
+        if n <= 1 || after.is_game_over() {
+            return Ok(None);
+        }
+
+        let mut defences = Vec::new();
+        for reply in after.legal_moves() {
+            let defence = UciMove::from_move(&reply, after.castles().mode()).to_string();
+            let mut next = after.clone();
+            next.play_unchecked(&reply);
+            let job = job.clone().moves(&[&mv, &defence]);
+            match prove(engine, job, next, n - 1).await? {
+                Some(proof) => defences.push((defence, proof)),
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(Proof { mv, defences }))
+    })
+}
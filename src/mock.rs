@@ -0,0 +1,173 @@
+use std::time::Duration;
+
+use anyhow::{Result, bail};
+use tokio::{
+    io::{self, AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
+    sync::oneshot,
+    time,
+};
+use tracing::trace;
+
+use crate::engine::Engine;
+
+#[derive(Debug, Clone)]
+enum Step {
+    Expect(String),
+    Send(String),
+    Delay(Duration),
+    Crash,
+}
+
+/// A scripted fake engine for deterministic tests.
+///
+/// The script is a sequence of commands the engine expects to receive and lines it answers
+/// with. It runs in-process over the same transport as a real engine, so nothing needs to be
+/// installed to exercise [`Engine`].
+///
+/// ```no_run
+/// # async fn example() -> anyhow::Result<()> {
+/// use uci::{engine::Go, mock::Script};
+///
+/// let (mut engine, mock) = Script::new()
+///     .expect("position startpos")
+///     .expect("go depth 10")
+///     .send("info depth 10 score cp 20 pv e2e4")
+///     .send("bestmove e2e4")
+///     .spawn();
+///
+/// let (info, best) = engine.go(Go::new()).await?;
+/// mock.finish().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct Script {
+    steps: Vec<Step>,
+}
+
+impl Script {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wait for the next command and fail unless it is exactly `cmd`.
+    pub fn expect(mut self, cmd: impl Into<String>) -> Self {
+        self.steps.push(Step::Expect(cmd.into()));
+        self
+    }
+
+    /// Write a line of output, which does not need to be valid UCI.
+    pub fn send(mut self, line: impl Into<String>) -> Self {
+        self.steps.push(Step::Send(line.into()));
+        self
+    }
+
+    pub fn delay(mut self, duration: Duration) -> Self {
+        self.steps.push(Step::Delay(duration));
+        self
+    }
+
+    /// Close the connection as if the engine process died.
+    pub fn crash(mut self) -> Self {
+        self.steps.push(Step::Crash);
+        self
+    }
+
+    /// Answer `uci` with an id and `uciok`.
+    pub fn handshake(self) -> Self {
+        self.expect("uci")
+            .send("id name Mock")
+            .send("id author uci")
+            .send("uciok")
+    }
+
+    /// Answer `isready` with `readyok`.
+    pub fn ready(self) -> Self {
+        self.expect("isready").send("readyok")
+    }
+
+    /// Start the script and return an engine connected to it.
+    pub fn spawn(self) -> (Engine, Mock) {
+        let (client, server) = io::duplex(4096);
+        let (client_rx, client_tx) = io::split(client);
+        let (server_rx, server_tx) = io::split(server);
+
+        let mock = self.serve(server_rx, server_tx);
+        (Engine::from_io(client_rx, client_tx), mock)
+    }
+
+    /// Start the script over any pair of streams, e.g. a socket.
+    pub fn serve(
+        self,
+        reader: impl AsyncRead + Unpin + Send + 'static,
+        writer: impl AsyncWrite + Unpin + Send + 'static,
+    ) -> Mock {
+        let (done_tx, done_rx) = oneshot::channel();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(reader).lines();
+            let mut writer = writer;
+
+            let result = self.run(&mut lines, &mut writer).await;
+            let crashed = !matches!(result, Ok(true));
+            _ = done_tx.send(result.map(|_| ()));
+
+            // Keep the connection open once the script is over, ignoring any further commands.
+            if !crashed {
+                while let Ok(Some(line)) = lines.next_line().await {
+                    trace!("mock ignored {line}");
+                }
+            }
+        });
+
+        Mock { done: done_rx }
+    }
+
+    /// Returns whether the connection should be kept open.
+    async fn run(
+        self,
+        lines: &mut io::Lines<BufReader<impl AsyncRead + Unpin>>,
+        writer: &mut (impl AsyncWrite + Unpin),
+    ) -> Result<bool> {
+        for step in self.steps {
+            match step {
+                Step::Expect(expected) => loop {
+                    match lines.next_line().await? {
+                        // Blank lines are ignored by engines, and by us.
+                        Some(line) if line.trim().is_empty() => continue,
+                        Some(line) if line == expected => {
+                            trace!("mock <- {line}");
+                            break;
+                        }
+                        Some(line) => bail!("expected `{expected}`, got `{line}`"),
+                        None => bail!("expected `{expected}`, connection closed"),
+                    }
+                },
+                Step::Send(mut line) => {
+                    trace!("mock -> {line}");
+                    line.push('\n');
+                    writer.write_all(line.as_bytes()).await?;
+                    writer.flush().await?;
+                }
+                Step::Delay(duration) => time::sleep(duration).await,
+                Step::Crash => return Ok(false),
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// A running [`Script`].
+#[derive(Debug)]
+pub struct Mock {
+    done: oneshot::Receiver<Result<()>>,
+}
+
+impl Mock {
+    /// Wait for the script to run to completion and report the first mismatch, if any.
+    pub async fn finish(self) -> Result<()> {
+        match self.done.await {
+            Ok(result) => result,
+            Err(_) => bail!("mock engine task was dropped"),
+        }
+    }
+}
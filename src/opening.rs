@@ -0,0 +1,255 @@
+//! An opening explorer tree built from PGN games, with engine evaluations, see [`Tree`].
+
+use std::{
+    collections::{BTreeMap, BTreeSet, btree_map::Entry},
+    io::Read,
+};
+
+use anyhow::{Result, bail};
+use shakmaty::{
+    EnPassantMode, Position,
+    fen::{Epd, Fen},
+    variant::{Variant, VariantPosition},
+};
+use tracing::{debug, warn};
+
+use crate::{
+    cache::Cache,
+    engine::{Engine, Go},
+    pgn::{self, Game},
+    search::Score,
+    variant::{self, play},
+};
+
+/// The games through a position or a move, by result.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Results {
+    /// Every game, unfinished ones included.
+    pub games: u32,
+    pub white: u32,
+    pub draws: u32,
+    pub black: u32,
+}
+
+impl Results {
+    fn add(&mut self, result: Option<&str>) {
+        self.games += 1;
+        match result {
+            Some("1-0") => self.white += 1,
+            Some("1/2-1/2") => self.draws += 1,
+            Some("0-1") => self.black += 1,
+            _ => {}
+        }
+    }
+}
+
+/// A move played from a position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Branch {
+    pub san: String,
+    pub results: Results,
+    /// The key of the position it leads to in [`Tree::nodes`].
+    pub to: String,
+}
+
+/// The engine opinion of a position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Eval {
+    pub depth: u32,
+    /// From the side to move, serialized as `"cp 20"` or `"mate 3"`.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "display"))]
+    pub score: Score,
+    pub pv: Vec<String>,
+}
+
+#[cfg(feature = "serde")]
+fn display<S: serde::Serializer>(score: &Score, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(score)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Node {
+    /// The position as first reached, with its move counters.
+    pub fen: String,
+    /// The fewest moves it was reached in.
+    pub ply: usize,
+    pub results: Results,
+    /// The moves played from here, by UCI notation.
+    pub moves: BTreeMap<String, Branch>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub eval: Option<Eval>,
+}
+
+/// Positions reached in a set of games, each with the moves played from it and how the games
+/// went. Positions are keyed by their FEN without the move counters, so transpositions share
+/// a node.
+///
+/// ```no_run
+/// # async fn example(engine: &mut uci::engine::Engine) -> anyhow::Result<()> {
+/// use uci::{cache::Cache, engine::Go, opening::Tree};
+///
+/// let mut tree = Tree::new(16);
+/// tree.read(std::fs::File::open("games.pgn")?)?;
+/// let cache = Cache::open("analysis.redb")?;
+/// tree.annotate(engine, &cache, Go::new().depth(20), 8).await?;
+/// # #[cfg(feature = "serde")]
+/// tree.write_json(std::io::stdout())?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Tree {
+    variant: Variant,
+    max_ply: usize,
+    pub nodes: BTreeMap<String, Node>,
+}
+
+impl Tree {
+    /// An empty tree of chess games, following each game for `max_ply` half-moves.
+    pub fn new(max_ply: usize) -> Self {
+        Self {
+            variant: Variant::Chess,
+            max_ply,
+            nodes: BTreeMap::new(),
+        }
+    }
+
+    /// Build the tree from games of another variant. Chess by default.
+    pub fn variant(mut self, variant: Variant) -> Self {
+        self.variant = variant;
+        self
+    }
+
+    /// The node of a position, in any format `Fen` parses.
+    pub fn get(&self, fen: &str) -> Option<&Node> {
+        let pos = variant::position(self.variant, Some(fen)).ok()?;
+        self.nodes.get(&key(&pos))
+    }
+
+    /// Add every game of a PGN database, returning how many were added.
+    ///
+    /// Games are read one at a time. Those that cannot be read or added, e.g. for an illegal
+    /// move, are skipped with a warning.
+    pub fn read(&mut self, pgn: impl Read) -> Result<usize> {
+        let (mut read, mut added) = (0, 0);
+        pgn::read_each(pgn, |game| {
+            read += 1;
+            match game.and_then(|game| self.add(&game)) {
+                Ok(()) => added += 1,
+                Err(e) => warn!(game = read, cause = %e, "skipping game"),
+            }
+            Ok(())
+        })?;
+        Ok(added)
+    }
+
+    /// Add the main line of a game, up to the maximum ply.
+    pub fn add(&mut self, game: &Game) -> Result<()> {
+        if game.variant != self.variant {
+            bail!(
+                "{} game in a {} tree",
+                game.variant.uci(),
+                self.variant.uci()
+            );
+        }
+        // A position or move repeated within the game counts it once.
+        let mut seen = BTreeSet::new();
+        let result = game.headers.get("Result").map(String::as_str);
+        let mut pos = game.start()?;
+        let mut from = self.visit(&pos, 0, result, &mut seen);
+        for (ply, mv) in game.moves.iter().take(self.max_ply).enumerate() {
+            play(&mut pos, &mv.uci)?;
+            let to = self.visit(&pos, ply + 1, result, &mut seen);
+            let node = self.nodes.get_mut(&from).expect("visited node");
+            let branch = node.moves.entry(mv.uci.clone()).or_insert_with(|| Branch {
+                san: mv.san.clone(),
+                results: Results::default(),
+                to: to.clone(),
+            });
+            if seen.insert(format!("{from} {}", mv.uci)) {
+                branch.results.add(result);
+            }
+            from = to;
+        }
+        Ok(())
+    }
+
+    /// Count a game through `pos` unless `seen` has its key already, returning the key.
+    fn visit(
+        &mut self,
+        pos: &VariantPosition,
+        ply: usize,
+        result: Option<&str>,
+        seen: &mut BTreeSet<String>,
+    ) -> String {
+        let key = key(pos);
+        let node = match self.nodes.entry(key.clone()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(Node {
+                fen: Fen::from_position(pos.clone(), EnPassantMode::Legal).to_string(),
+                ply,
+                results: Results::default(),
+                moves: BTreeMap::new(),
+                eval: None,
+            }),
+        };
+        node.ply = node.ply.min(ply);
+        if seen.insert(key.clone()) {
+            node.results.add(result);
+        }
+        key
+    }
+
+    /// Evaluate every position up to `max_ply` that has none yet, nearest to the start first,
+    /// with the limits of `limit` and through the cache. Positions where the game is over are
+    /// skipped. Returns how many positions were evaluated.
+    pub async fn annotate(
+        &mut self,
+        engine: &mut Engine,
+        cache: &Cache,
+        limit: Go,
+        max_ply: usize,
+    ) -> Result<usize> {
+        let mut todo: Vec<(usize, String)> = self
+            .nodes
+            .iter()
+            .filter(|(_, node)| node.ply <= max_ply && node.eval.is_none())
+            .map(|(key, node)| (node.ply, key.clone()))
+            .collect();
+        todo.sort();
+
+        let mut evaluated = 0;
+        for (ply, key) in todo {
+            let node = self.nodes.get_mut(&key).expect("collected node");
+            let pos = variant::position(self.variant, Some(&node.fen))?;
+            if pos.is_game_over() {
+                continue;
+            }
+            debug!(ply, fen = node.fen, "evaluating opening position");
+            let job = limit.clone().fen(&node.fen).variant(self.variant);
+            let (info, _) = cache.go(engine, job).await?;
+            node.eval = Some(Eval {
+                depth: info.depth,
+                score: info.score.unwrap_or_default(),
+                pv: info.pv,
+            });
+            evaluated += 1;
+        }
+        Ok(evaluated)
+    }
+
+    /// Write the nodes as a JSON object keyed by position.
+    #[cfg(feature = "serde")]
+    pub fn write_json(&self, w: impl std::io::Write) -> Result<()> {
+        serde_json::to_writer_pretty(w, &self.nodes)?;
+        Ok(())
+    }
+}
+
+fn key(pos: &VariantPosition) -> String {
+    Epd::from_position(pos.clone(), EnPassantMode::Legal).to_string()
+}
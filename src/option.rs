@@ -0,0 +1,135 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::{Context, Result, bail};
+
+/// The type of an engine option, with its default and constraints.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "lowercase"))]
+pub enum OptionKind {
+    Check { default: bool },
+    Spin { default: i64, min: i64, max: i64 },
+    Combo { default: String, vars: Vec<String> },
+    Button,
+    String { default: String },
+}
+
+/// An option as advertised by an engine in response to `uci`.
+///
+/// ```
+/// use uci::option::{OptionKind, UciOption};
+///
+/// let opt: UciOption = "option name Skill Level type spin default 20 min 0 max 20".parse()?;
+/// assert_eq!(opt.name, "Skill Level");
+/// assert_eq!(opt.kind, OptionKind::Spin { default: 20, min: 0, max: 20 });
+/// # anyhow::Ok(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UciOption {
+    pub name: String,
+    /// Flattened when serialized, e.g. `{ "name": "Hash", "type": "spin", ... }`.
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub kind: OptionKind,
+}
+
+impl UciOption {
+    pub fn new(name: impl Into<String>, kind: OptionKind) -> Self {
+        Self {
+            name: name.into(),
+            kind,
+        }
+    }
+}
+
+/// Split `s` on the given keywords, returning the words following each of them.
+///
+/// Values may span several words, like option names, so they run until the next keyword.
+fn fields(s: &str, keywords: &[&'static str]) -> Vec<(&'static str, String)> {
+    let mut fields: Vec<(&'static str, String)> = Vec::new();
+    for word in s.split_whitespace() {
+        match keywords.iter().find(|k| **k == word) {
+            Some(keyword) => fields.push((keyword, String::new())),
+            None => {
+                if let Some((_, value)) = fields.last_mut() {
+                    if !value.is_empty() {
+                        value.push(' ');
+                    }
+                    value.push_str(word);
+                }
+            }
+        }
+    }
+    fields
+}
+
+fn parse_option(line: &str) -> Result<UciOption> {
+    let rest = line.strip_prefix("option ").context("not an option")?;
+    let fields = fields(rest, &["name", "type", "default", "min", "max", "var"]);
+    let get = |key: &str| {
+        fields
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, v)| v.as_str())
+    };
+    let default = || get("default").context("no default");
+
+    let name = get("name").context("no name")?.to_string();
+    let kind = match get("type").context("no type")? {
+        "check" => OptionKind::Check {
+            default: default()?.parse()?,
+        },
+        "spin" => OptionKind::Spin {
+            default: default()?.parse()?,
+            min: get("min").context("no min")?.parse()?,
+            max: get("max").context("no max")?.parse()?,
+        },
+        "combo" => OptionKind::Combo {
+            default: default()?.into(),
+            vars: fields
+                .iter()
+                .filter(|(k, _)| *k == "var")
+                .map(|(_, v)| v.clone())
+                .collect(),
+        },
+        "button" => OptionKind::Button,
+        "string" => OptionKind::String {
+            default: match get("default").unwrap_or_default() {
+                "<empty>" => String::new(),
+                default => default.into(),
+            },
+        },
+        other => bail!("unknown option type: {other}"),
+    };
+
+    Ok(UciOption { name, kind })
+}
+
+impl FromStr for UciOption {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        parse_option(s)
+    }
+}
+
+impl fmt::Display for UciOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "option name {} type ", self.name)?;
+        match &self.kind {
+            OptionKind::Check { default } => write!(f, "check default {default}"),
+            OptionKind::Spin { default, min, max } => {
+                write!(f, "spin default {default} min {min} max {max}")
+            }
+            OptionKind::Combo { default, vars } => {
+                write!(f, "combo default {default}")?;
+                vars.iter().try_for_each(|var| write!(f, " var {var}"))
+            }
+            OptionKind::Button => write!(f, "button"),
+            OptionKind::String { default } if default.is_empty() => {
+                write!(f, "string default <empty>")
+            }
+            OptionKind::String { default } => write!(f, "string default {default}"),
+        }
+    }
+}
@@ -0,0 +1,150 @@
+//! Games read from PGN, ready to be analysed move by move.
+
+use std::{collections::BTreeMap, io::Read};
+
+use anyhow::{Context, Result, anyhow};
+use pgn_reader::{BufferedReader, RawHeader, SanPlus, Skip, Visitor};
+use shakmaty::{
+    Position,
+    uci::UciMove,
+    variant::{Variant, VariantPosition},
+};
+
+use crate::{engine::Go, variant};
+
+/// A move of the main line, in both notations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ply {
+    pub san: String,
+    /// In the castling notation of the game, king takes rook for Chess960.
+    pub uci: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Game {
+    pub headers: BTreeMap<String, String>,
+    /// From the `Variant` header, chess without one.
+    pub variant: Variant,
+    /// From the `FEN` header, the start position of the variant without one.
+    pub fen: Option<String>,
+    pub moves: Vec<Ply>,
+}
+
+impl Game {
+    pub fn start(&self) -> Result<VariantPosition> {
+        variant::position(self.variant, self.fen.as_deref())
+    }
+
+    /// A search of the position before the move at `ply`, or after the last move for any `ply`
+    /// past the end.
+    pub fn go(&self, ply: usize) -> Go {
+        let ply = ply.min(self.moves.len());
+        let moves: Vec<&str> = self.moves[..ply].iter().map(|m| m.uci.as_str()).collect();
+        let job = Go::default().moves(&moves).variant(self.variant);
+        match &self.fen {
+            Some(fen) => job.fen(fen),
+            None => job,
+        }
+    }
+}
+
+/// Read every game, stopping at the first one with an unknown variant or an illegal move.
+pub fn read_games(pgn: impl Read) -> Result<Vec<Game>> {
+    let mut games = Vec::new();
+    read_each(pgn, |game| {
+        games.push(game.with_context(|| format!("in game {}", games.len() + 1))?);
+        Ok(())
+    })?;
+    Ok(games)
+}
+
+/// Hand every game to `f` as it is read, or why it could not be, without keeping them around.
+///
+/// Stops at the first read error, or at the first error `f` returns.
+pub fn read_each(pgn: impl Read, mut f: impl FnMut(Result<Game>) -> Result<()>) -> Result<()> {
+    let mut reader = BufferedReader::new(pgn);
+    while let Some(game) = reader.read_game(&mut Reader::default())? {
+        f(game)?;
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+struct Reader {
+    headers: BTreeMap<String, String>,
+    game: Option<Game>,
+    pos: Option<VariantPosition>,
+    error: Option<anyhow::Error>,
+}
+
+impl Reader {
+    fn fail(&mut self, e: anyhow::Error) -> Skip {
+        self.error.get_or_insert(e);
+        Skip(true)
+    }
+}
+
+impl Visitor for Reader {
+    type Result = Result<Game>;
+
+    fn header(&mut self, key: &[u8], value: RawHeader<'_>) {
+        self.headers.insert(
+            String::from_utf8_lossy(key).into(),
+            value.decode_utf8_lossy().into(),
+        );
+    }
+
+    fn end_headers(&mut self) -> Skip {
+        let variant = match self.headers.get("Variant") {
+            Some(name) => match Variant::from_ascii(name.as_bytes()) {
+                Ok(variant) => variant,
+                Err(_) => return self.fail(anyhow!("unknown variant: {name}")),
+            },
+            None => Variant::Chess,
+        };
+        let game = Game {
+            fen: self.headers.get("FEN").cloned(),
+            headers: std::mem::take(&mut self.headers),
+            variant,
+            moves: Vec::new(),
+        };
+        match game.start() {
+            Ok(pos) => self.pos = Some(pos),
+            Err(e) => return self.fail(e),
+        }
+        self.game = Some(game);
+        Skip(false)
+    }
+
+    fn san(&mut self, san_plus: SanPlus) {
+        let (Some(pos), Some(game)) = (&mut self.pos, &mut self.game) else {
+            return;
+        };
+        match san_plus.san.to_move(pos) {
+            Ok(m) => {
+                game.moves.push(Ply {
+                    san: san_plus.to_string(),
+                    uci: UciMove::from_move(&m, pos.castles().mode()).to_string(),
+                });
+                pos.play_unchecked(&m);
+            }
+            Err(_) => {
+                let ply = game.moves.len() + 1;
+                self.pos = None;
+                self.error
+                    .get_or_insert(anyhow!("illegal move {san_plus} at ply {ply}"));
+            }
+        }
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        Skip(true)
+    }
+
+    fn end_game(&mut self) -> Self::Result {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        self.game.take().context("game without headers")
+    }
+}
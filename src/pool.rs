@@ -0,0 +1,169 @@
+use std::{
+    ops::{Deref, DerefMut},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use anyhow::{Context, Result};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::warn;
+
+use crate::{
+    backend::ChessEngine,
+    engine::{Engine, EngineBuilder},
+};
+
+struct Shared<E> {
+    idle: Mutex<Vec<E>>,
+    available: Arc<Semaphore>,
+    size: usize,
+    /// Engines idle or checked out, the others died.
+    live: AtomicUsize,
+}
+
+impl<E> Shared<E> {
+    /// Take a dead engine out of the pool, closing it when none are left so that waiters fail
+    /// instead of waiting forever.
+    fn remove(&self, permit: OwnedSemaphorePermit) {
+        permit.forget();
+        if self.live.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.available.close();
+        }
+    }
+}
+
+/// A fixed set of engines shared between tasks.
+///
+/// Engines are checked out with [`EnginePool::get`] and go back to the pool when the returned
+/// guard is dropped, after being stopped so the next user gets an idle engine. A guard dropped
+/// outside a Tokio runtime cannot stop its engine, which is removed from the pool instead.
+///
+/// Engines that die are not replaced. Once all of them are gone, [`EnginePool::get`] fails.
+///
+/// Any [`ChessEngine`] can be pooled, UCI [`Engine`]s by default.
+pub struct EnginePool<E = Engine> {
+    shared: Arc<Shared<E>>,
+}
+
+impl<E> Clone for EnginePool<E> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl EnginePool {
+    /// Spawn `size` engines from the same builder, handshake included.
+    pub async fn spawn(builder: &EngineBuilder, size: usize) -> Result<Self> {
+        let mut engines = Vec::with_capacity(size);
+        for _ in 0..size {
+            engines.push(builder.clone().spawn().await?);
+        }
+        Ok(Self::new(engines))
+    }
+}
+
+impl<E: ChessEngine> EnginePool<E> {
+    pub fn new(engines: Vec<E>) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                available: Arc::new(Semaphore::new(engines.len())),
+                size: engines.len(),
+                live: AtomicUsize::new(engines.len()),
+                idle: Mutex::new(engines),
+            }),
+        }
+    }
+
+    /// Wait for an idle engine, failing if every engine died.
+    pub async fn get(&self) -> Result<PooledEngine<E>> {
+        let permit = self
+            .shared
+            .available
+            .clone()
+            .acquire_owned()
+            .await
+            .context("every engine in the pool died")?;
+        let engine = self
+            .shared
+            .idle
+            .lock()
+            .unwrap()
+            .pop()
+            .context("engine pool is empty")?;
+
+        Ok(PooledEngine {
+            engine: Some(engine),
+            shared: self.shared.clone(),
+            permit: Some(permit),
+        })
+    }
+
+    /// The number of engines the pool started with.
+    pub fn size(&self) -> usize {
+        self.shared.size
+    }
+
+    /// The number of engines still alive, idle or not.
+    pub fn live(&self) -> usize {
+        self.shared.live.load(Ordering::SeqCst)
+    }
+
+    /// The number of engines currently idle.
+    pub fn idle(&self) -> usize {
+        self.shared.available.available_permits()
+    }
+}
+
+/// An engine checked out of an [`EnginePool`].
+pub struct PooledEngine<E: ChessEngine = Engine> {
+    engine: Option<E>,
+    shared: Arc<Shared<E>>,
+    permit: Option<OwnedSemaphorePermit>,
+}
+
+impl<E: ChessEngine> Deref for PooledEngine<E> {
+    type Target = E;
+
+    fn deref(&self) -> &E {
+        self.engine.as_ref().unwrap()
+    }
+}
+
+impl<E: ChessEngine> DerefMut for PooledEngine<E> {
+    fn deref_mut(&mut self) -> &mut E {
+        self.engine.as_mut().unwrap()
+    }
+}
+
+impl<E: ChessEngine> Drop for PooledEngine<E> {
+    fn drop(&mut self) {
+        let (Some(mut engine), Some(permit)) = (self.engine.take(), self.permit.take()) else {
+            return;
+        };
+        let shared = self.shared.clone();
+        // Without a runtime the engine cannot be stopped, so it leaves the pool like a dead one.
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            warn!("engine dropped outside a runtime, removing it from the pool");
+            self.shared.remove(permit);
+            return;
+        };
+
+        // The guard may be dropped mid-search, e.g. when a client goes away.
+        runtime.spawn(async move {
+            match engine.stop().await {
+                Ok(_) => {
+                    shared.idle.lock().unwrap().push(engine);
+                    drop(permit);
+                }
+                Err(e) => {
+                    warn!(cause = %e, "dropping dead engine from pool");
+                    shared.remove(permit);
+                }
+            }
+        });
+    }
+}
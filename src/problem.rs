@@ -0,0 +1,174 @@
+//! Soundness checks for composed directmates, built on the mate searches of [`crate::mate`].
+//!
+//! A directmate in N is sound when a single key mates in exactly N moves, and after the key
+//! every defence has a single mating continuation, down to the mate. Alternative keys are
+//! cooks, keys that mate sooner are short mates, and alternative continuations after a defence
+//! are duals.
+
+use std::pin::Pin;
+
+use anyhow::{Context, Result, bail};
+use shakmaty::{Position, uci::UciMove, variant::VariantPosition};
+
+use crate::{
+    engine::{Engine, Go},
+    variant::{self, play},
+};
+
+/// White has alternatives after the last move of `line`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dual {
+    /// The moves after the key, ending on a defence.
+    pub line: Vec<String>,
+    /// Every move that still mates in time, with the length of its mate.
+    pub continuations: Vec<(String, u32)>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    /// Every first move that mates in at most N moves, with the length of its mate.
+    pub keys: Vec<(String, u32)>,
+    /// The keys other than the intended one, or all of them when there is more than one and
+    /// none was given.
+    pub cooks: Vec<String>,
+    /// The keys that mate in fewer than N moves.
+    pub short_mates: Vec<(String, u32)>,
+    /// The defences with more than one mating continuation.
+    pub duals: Vec<Dual>,
+    /// The lines after the key, ending on a defence without any mating continuation found.
+    pub refutations: Vec<Vec<String>>,
+}
+
+impl Report {
+    pub fn is_sound(&self) -> bool {
+        self.keys.len() == 1
+            && self.cooks.is_empty()
+            && self.short_mates.is_empty()
+            && self.duals.is_empty()
+            && self.refutations.is_empty()
+    }
+}
+
+/// Check a directmate in `moves` moves, the side to move of `job` being the one that mates.
+///
+/// Every legal first move is searched on its own with `go mate` and `searchmoves`. The key,
+/// `key` or the only one found, is then played against every defence, and each reply is
+/// searched the same way for the mate in one move fewer. A single continuation is followed to
+/// the mate the same way, duals and refutations end a line. Mates in one are found without the
+/// engine.
+///
+/// The limits of the job apply to every search, use `Go::default()` to search until the mate
+/// is found or ruled out.
+pub async fn check(engine: &mut Engine, job: Go, moves: u32, key: Option<&str>) -> Result<Report> {
+    if moves == 0 {
+        bail!("a directmate takes at least one move");
+    }
+    let variant = job.variant.unwrap_or(engine.variant());
+    let mut pos = variant::position(variant, job.fen.as_deref())?;
+    for m in variant::line(&pos, &job.moves)? {
+        pos.play_unchecked(&m);
+    }
+
+    let mut report = Report {
+        keys: mating_moves(engine, &job, &pos, moves).await?,
+        ..Default::default()
+    };
+    report.short_mates = report
+        .keys
+        .iter()
+        .filter(|(_, n)| *n < moves)
+        .cloned()
+        .collect();
+    report.cooks = match key {
+        Some(key) => report
+            .keys
+            .iter()
+            .map(|(mv, _)| mv.clone())
+            .filter(|mv| mv != key)
+            .collect(),
+        None if report.keys.len() > 1 => report.keys.iter().map(|(mv, _)| mv.clone()).collect(),
+        None => Vec::new(),
+    };
+
+    let key = match (key, report.keys.as_slice()) {
+        (Some(key), _) => key.to_string(),
+        (None, [(key, _)]) => key.clone(),
+        (None, _) => return Ok(report),
+    };
+    play(&mut pos, &key)?;
+    let job = job.moves(&[&key]);
+    defences(engine, &job, pos, Vec::new(), moves - 1, &mut report).await?;
+    Ok(report)
+}
+
+type DefencesFuture<'a> = Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>>;
+
+/// Check every defence from `pos`, reached by `line` after the key, for a single continuation
+/// that mates in at most `moves` moves, and follow it.
+fn defences<'a>(
+    engine: &'a mut Engine,
+    job: &'a Go,
+    pos: VariantPosition,
+    line: Vec<String>,
+    moves: u32,
+    report: &'a mut Report,
+) -> DefencesFuture<'a> {
+    Box::pin(async move {
+        if moves == 0 || pos.is_game_over() {
+            return Ok(());
+        }
+
+        for reply in pos.legal_moves() {
+            let mut line = line.clone();
+            line.push(UciMove::from_move(&reply, pos.castles().mode()).to_string());
+            let mut after = pos.clone();
+            after.play_unchecked(&reply);
+            let continuations =
+                mating_moves(engine, &job.clone().moves(&line), &after, moves).await?;
+            match continuations.as_slice() {
+                [] => report.refutations.push(line),
+                [(mv, n)] => {
+                    play(&mut after, mv)?;
+                    line.push(mv.clone());
+                    defences(engine, job, after, line, n - 1, report).await?;
+                }
+                _ => report.duals.push(Dual {
+                    line,
+                    continuations,
+                }),
+            }
+        }
+        Ok(())
+    })
+}
+
+/// The moves from `pos`, reached by `job`, that mate in at most `moves` moves.
+async fn mating_moves(
+    engine: &mut Engine,
+    job: &Go,
+    pos: &VariantPosition,
+    moves: u32,
+) -> Result<Vec<(String, u32)>> {
+    let mut mating = Vec::new();
+    for m in pos.legal_moves() {
+        let mv = UciMove::from_move(&m, pos.castles().mode()).to_string();
+        let mut after = pos.clone();
+        after.play_unchecked(&m);
+        if after.is_checkmate() {
+            mating.push((mv, 1));
+            continue;
+        }
+        if moves <= 1 || after.is_game_over() {
+            continue;
+        }
+
+        let search = job.clone().searchmoves(&[&mv]);
+        if let Some((n, info)) = engine.go_mate(search, moves).await? {
+            let first = info.pv.first().context("mate without a move")?;
+            if *first == mv {
+                mating.push((mv, n));
+            }
+        }
+    }
+    Ok(mating)
+}
@@ -0,0 +1,172 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+
+use crate::engine::{Engine, EngineBuilder, Go};
+
+/// Environment variable pointing at the profiles file, see [`Profiles::find`].
+pub const PROFILES_ENV: &str = "UCI_PROFILES";
+
+/// The value of an engine option, as written in a profile.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum OptionValue {
+    Bool(bool),
+    Int(i64),
+    String(String),
+}
+
+impl fmt::Display for OptionValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bool(b) => write!(f, "{b}"),
+            Self::Int(n) => write!(f, "{n}"),
+            Self::String(s) => write!(f, "{s}"),
+        }
+    }
+}
+
+/// The search limits used when none are given explicitly.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Limits {
+    pub depth: Option<u32>,
+    pub movetime: Option<u64>,
+}
+
+/// A named engine setup.
+///
+/// ```toml
+/// [sf-16-8t]
+/// path = "stockfish"
+/// options = { Threads = 8, Hash = 1024, SyzygyPath = "/tb" }
+/// limits = { depth = 25 }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// The working directory, e.g. where the network files live.
+    pub dir: Option<PathBuf>,
+    #[serde(default)]
+    pub options: BTreeMap<String, OptionValue>,
+    #[serde(default)]
+    pub limits: Limits,
+}
+
+impl Profile {
+    pub fn builder(&self) -> EngineBuilder {
+        let mut builder = EngineBuilder::new(&self.path).args(&self.args);
+        for (key, value) in &self.env {
+            builder = builder.env(key, value);
+        }
+        if let Some(dir) = &self.dir {
+            builder = builder.current_dir(dir);
+        }
+        for (name, value) in &self.options {
+            builder = builder.option(name, value);
+        }
+        builder
+    }
+
+    /// A search with the profile limits, or [`Go::new`] if it has none.
+    pub fn go(&self) -> Go {
+        let Limits { depth, movetime } = self.limits;
+        if depth.is_none() && movetime.is_none() {
+            return Go::new();
+        }
+
+        let mut go = Go::default();
+        if let Some(depth) = depth {
+            go = go.depth(depth);
+        }
+        if let Some(movetime) = movetime {
+            go = go.movetime(movetime);
+        }
+        go
+    }
+}
+
+/// A set of profiles loaded from a TOML or JSON file, keyed by name.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(transparent)]
+pub struct Profiles {
+    profiles: BTreeMap<String, Profile>,
+}
+
+impl Profiles {
+    pub fn from_toml(s: &str) -> Result<Self> {
+        Ok(toml::from_str(s)?)
+    }
+
+    pub fn from_json(s: &str) -> Result<Self> {
+        Ok(serde_json::from_str(s)?)
+    }
+
+    /// Load a file, parsed as JSON if it has a `.json` extension and as TOML otherwise.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let s = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let profiles = match path.extension() {
+            Some(ext) if ext == "json" => Self::from_json(&s),
+            _ => Self::from_toml(&s),
+        };
+        profiles.with_context(|| format!("invalid profiles in {}", path.display()))
+    }
+
+    /// Load the first profiles file found in:
+    ///
+    /// 1. the path in `UCI_PROFILES`,
+    /// 2. `uci.toml` or `uci.json` in the current directory,
+    /// 3. `$XDG_CONFIG_HOME/uci/profiles.toml`, falling back to `~/.config`.
+    pub fn find() -> Result<Self> {
+        if let Some(path) = std::env::var_os(PROFILES_ENV) {
+            return Self::load(path);
+        }
+
+        let mut candidates = vec![PathBuf::from("uci.toml"), PathBuf::from("uci.json")];
+        let config = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")));
+        if let Some(config) = config {
+            candidates.push(config.join("uci").join("profiles.toml"));
+        }
+
+        match candidates.iter().find(|path| path.is_file()) {
+            Some(path) => Self::load(path),
+            None => bail!("no profiles file found, set {PROFILES_ENV} or create uci.toml"),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Result<&Profile> {
+        self.profiles
+            .get(name)
+            .with_context(|| format!("unknown profile: {name}"))
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.profiles.keys().map(String::as_str)
+    }
+
+    /// Spawn the engine of a profile and run the handshake with its options.
+    pub async fn spawn(&self, name: &str) -> Result<Engine> {
+        self.get(name)?.builder().spawn().await
+    }
+}
+
+impl Engine {
+    /// Spawn an engine from a profile in the default profiles file, see [`Profiles::find`].
+    pub async fn from_profile(name: &str) -> Result<Self> {
+        Profiles::find()?.spawn(name).await
+    }
+}
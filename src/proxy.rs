@@ -0,0 +1,177 @@
+//! A man in the middle between a GUI and an engine, see the `uci-proxy` binary.
+
+use std::{collections::BTreeMap, io::Write, time::Instant};
+
+use anyhow::Result;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
+    select, time,
+};
+
+use crate::{
+    engine::{Engine, QUIT_TIMEOUT},
+    option::{OptionKind, UciOption},
+    server::Command,
+};
+
+/// Rules applied to the lines going through the proxy.
+///
+/// Option names are matched case-insensitively, like engines do.
+#[derive(Debug, Default, Clone)]
+pub struct Rewrite {
+    force: BTreeMap<String, (String, String)>,
+    cap: BTreeMap<String, i64>,
+    name: Option<String>,
+}
+
+impl Rewrite {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Always use `value` for the option, whatever the GUI asks for.
+    pub fn force(mut self, name: impl Into<String>, value: impl ToString) -> Self {
+        let name = name.into();
+        self.force
+            .insert(name.to_lowercase(), (name, value.to_string()));
+        self
+    }
+
+    /// Never let a spin option exceed `max`.
+    pub fn cap(mut self, name: impl AsRef<str>, max: i64) -> Self {
+        self.cap.insert(name.as_ref().to_lowercase(), max);
+        self
+    }
+
+    /// Replace the `id name` of the engine.
+    pub fn rename(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// The `setoption` commands for the forced options.
+    pub fn forced(&self) -> Vec<String> {
+        self.force
+            .values()
+            .map(|(name, value)| format!("setoption name {name} value {value}"))
+            .collect()
+    }
+
+    /// Rewrite a command from the GUI before it reaches the engine.
+    pub fn command(&self, line: &str) -> String {
+        let Ok(Command::SetOption { name, value }) = line.parse() else {
+            return line.into();
+        };
+        let key = name.to_lowercase();
+
+        if let Some((_, forced)) = self.force.get(&key) {
+            return format!("setoption name {name} value {forced}");
+        }
+        if let (Some(max), Some(value)) = (self.cap.get(&key), &value)
+            && let Ok(n) = value.parse::<i64>()
+            && n > *max
+        {
+            return format!("setoption name {name} value {max}");
+        }
+        line.into()
+    }
+
+    /// Rewrite a line from the engine before it reaches the GUI.
+    pub fn response(&self, line: &str) -> String {
+        if let (Some(name), Some(_)) = (&self.name, line.strip_prefix("id name ")) {
+            return format!("id name {name}");
+        }
+        if let Ok(mut option) = line.parse::<UciOption>()
+            && let Some(cap) = self.cap.get(&option.name.to_lowercase())
+            && let OptionKind::Spin { default, min, max } = &mut option.kind
+        {
+            *max = (*max).min(*cap).max(*min);
+            *default = (*default).min(*max);
+            return option.to_string();
+        }
+        line.into()
+    }
+}
+
+/// Writes every line going through the proxy, prefixed with the seconds since start.
+pub struct Log {
+    start: Instant,
+    out: Box<dyn Write + Send>,
+}
+
+impl Log {
+    pub fn new(out: impl Write + Send + 'static) -> Self {
+        Self {
+            start: Instant::now(),
+            out: Box::new(out),
+        }
+    }
+
+    fn line(&mut self, arrow: &str, line: &str) {
+        let t = self.start.elapsed().as_secs_f64();
+        _ = writeln!(self.out, "[{t:10.3}] {arrow} {line}");
+        _ = self.out.flush();
+    }
+
+    /// A line as received, and as forwarded if it was rewritten.
+    fn forward(&mut self, arrow: &str, line: &str, rewritten: &str) {
+        if line == rewritten {
+            self.line(arrow, line);
+        } else {
+            self.line(arrow, &format!("{line}  =>  {rewritten}"));
+        }
+    }
+}
+
+/// Forward lines between the GUI and the engine until either side hangs up.
+///
+/// After `quit` the engine output is still forwarded until it closes, for up to a second,
+/// then an engine process that did not exit is killed.
+pub async fn run(
+    mut engine: Engine,
+    rewrite: Rewrite,
+    mut log: Log,
+    input: impl AsyncRead + Unpin,
+    mut output: impl AsyncWrite + Unpin,
+) -> Result<()> {
+    let mut lines = BufReader::new(input).lines();
+    let mut quit = None;
+
+    loop {
+        select! {
+            line = lines.next_line(), if quit.is_none() => {
+                let Some(line) = line? else { break };
+                let cmd = rewrite.command(&line);
+                log.forward("->", &line, &cmd);
+                if cmd.trim() == "quit" {
+                    quit = Some(time::Instant::now() + QUIT_TIMEOUT);
+                }
+                engine.tx.send(cmd).await?;
+            }
+            _ = time::sleep_until(quit.unwrap_or_else(time::Instant::now)), if quit.is_some() => break,
+            line = engine.rx.recv() => {
+                let Some(line) = line else { break };
+                let response = rewrite.response(&line);
+                log.forward("<-", &line, &response);
+
+                // Apply the forced options before the GUI gets to configure the engine.
+                if line == "uciok" {
+                    for cmd in rewrite.forced() {
+                        log.line("=>", &cmd);
+                        engine.tx.send(cmd).await?;
+                    }
+                }
+
+                output.write_all(response.as_bytes()).await?;
+                output.write_all(b"\n").await?;
+                output.flush().await?;
+            }
+        }
+    }
+
+    if let Some(deadline) = quit {
+        let left = deadline.saturating_duration_since(time::Instant::now());
+        engine.reap(left).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,560 @@
+//! Tactical puzzles found in analysed games with [`Extractor`] and checked with [`Validator`].
+
+use std::{fmt, io::Write};
+
+use anyhow::{Result, bail};
+use shakmaty::{
+    Color, EnPassantMode, Position,
+    fen::Fen,
+    variant::{Variant, VariantPosition},
+};
+use tracing::debug;
+
+use crate::{
+    engine::{Engine, Go},
+    epd::csv_field,
+    pgn::Game,
+    search::{Info, Score},
+    variant::{self, play},
+};
+
+/// A score as centipawns, with mates beyond any material advantage and shorter mates first.
+pub(crate) fn centipawns(score: Score) -> i32 {
+    match score {
+        Score::Cp(cp) => cp,
+        Score::Mate(moves) if moves > 0 => 100_000 - moves,
+        Score::Mate(moves) => -100_000 - moves,
+    }
+}
+
+/// The material of a side in pawns, counting knights and bishops as 3, rooks as 5 and queens
+/// as 9.
+pub(crate) fn material(pos: &VariantPosition, color: Color) -> i32 {
+    let m = pos.board().material_side(color);
+    i32::from(m.pawn)
+        + 3 * i32::from(m.knight + m.bishop)
+        + 5 * i32::from(m.rook)
+        + 9 * i32::from(m.queen)
+}
+
+fn describe(info: &Info) -> String {
+    match info.score {
+        Some(score) => format!("{} ({score})", info.pv.join(" ")),
+        None => info.pv.join(" "),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Theme {
+    /// The solution ends in mate after this many solver moves.
+    MateIn(usize),
+    /// The solver ends up at least two pawns of material ahead.
+    MaterialWin,
+}
+
+/// Written the way Lichess names its themes, e.g. `mateIn2`.
+impl fmt::Display for Theme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MateIn(n) => write!(f, "mateIn{n}"),
+            Self::MaterialWin => write!(f, "materialWin"),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Theme {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// A position where a blunder allows a single winning continuation.
+///
+/// With the `serde` feature, serializes as `{"fen": "...", "moves": ["f3e5", "g8f6"], "ply": 12, "themes": ["mateIn1"]}`,
+/// plus `"variant": "atomic"` when the variant is known.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Puzzle {
+    /// The position before the blunder.
+    pub fen: String,
+    /// The blunder, then the solution alternating with the best replies, ending on a solver
+    /// move.
+    pub moves: Vec<String>,
+    /// The index of the blunder in the moves of the game.
+    pub ply: usize,
+    pub themes: Vec<Theme>,
+    /// The variant of the game it was found in, `None` for puzzles from elsewhere.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", serialize_with = "uci_name")
+    )]
+    pub variant: Option<Variant>,
+}
+
+#[cfg(feature = "serde")]
+fn uci_name<S: serde::Serializer>(
+    variant: &Option<Variant>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(variant.unwrap_or_default().uci())
+}
+
+impl Puzzle {
+    /// A puzzle from elsewhere, without a game ply or themes.
+    pub fn new(fen: impl Into<String>, moves: &[impl AsRef<str>]) -> Self {
+        Self {
+            fen: fen.into(),
+            moves: moves.iter().map(|mv| mv.as_ref().into()).collect(),
+            ply: 0,
+            themes: Vec::new(),
+            variant: None,
+        }
+    }
+
+    /// The moves the solver has to find, and the replies in between.
+    pub fn solution(&self) -> &[String] {
+        self.moves.get(1..).unwrap_or_default()
+    }
+}
+
+/// Write the puzzles as CSV, with the moves and themes separated by spaces.
+pub fn write_csv(puzzles: &[Puzzle], mut w: impl Write) -> Result<()> {
+    writeln!(w, "fen,moves,ply,themes")?;
+    for puzzle in puzzles {
+        let themes: Vec<String> = puzzle.themes.iter().map(Theme::to_string).collect();
+        writeln!(
+            w,
+            "{},{},{},{}",
+            csv_field(&puzzle.fen),
+            puzzle.moves.join(" "),
+            puzzle.ply,
+            themes.join(" "),
+        )?;
+    }
+    Ok(())
+}
+
+/// Read puzzles from CSV, as written by [`write_csv`] or from the Lichess puzzle database.
+///
+/// Only the fen and moves columns are used, an optional header line is skipped. A
+/// `PuzzleId,FEN,Moves,...` header, as in the Lichess database, also skips the id column.
+pub fn read_csv(csv: &str) -> Result<Vec<Puzzle>> {
+    let lichess = csv.starts_with("PuzzleId,");
+    csv.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .filter(|(n, line)| *n > 0 || !(lichess || line.starts_with("fen,")))
+        .map(|(n, line)| {
+            let mut fields = line.split(',').skip(usize::from(lichess));
+            let (Some(fen), Some(moves)) = (fields.next(), fields.next()) else {
+                bail!("line {}: expected a fen and moves", n + 1);
+            };
+            let moves: Vec<&str> = moves.split_whitespace().collect();
+            if moves.len() < 2 {
+                bail!("line {}: expected a move and a solution", n + 1);
+            }
+            Ok(Puzzle::new(fen.trim_matches('"'), &moves))
+        })
+        .collect()
+}
+
+/// Write the puzzles as a JSON array.
+#[cfg(feature = "serde")]
+pub fn write_json(puzzles: &[Puzzle], w: impl Write) -> Result<()> {
+    serde_json::to_writer_pretty(w, puzzles)?;
+    Ok(())
+}
+
+/// Finds puzzles in games: a move that turns a position that was not winning for the opponent
+/// into a winning one, followed by a line where every solver move is the only one that wins.
+///
+/// Every solver move is checked with `MultiPV 2`, the line stops before the first solver move
+/// that has a good enough alternative, or after `max_moves` of them.
+///
+/// ```no_run
+/// # async fn example(engine: &mut uci::engine::Engine, game: &uci::pgn::Game) -> anyhow::Result<()> {
+/// use uci::{engine::Go, puzzle::Extractor};
+///
+/// let puzzles = Extractor::new(Go::new().depth(18)).extract(engine, game).await?;
+/// uci::puzzle::write_csv(&puzzles, std::io::stdout())?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Extractor {
+    limit: Go,
+    winning: i32,
+    margin: i32,
+    max_moves: usize,
+}
+
+impl Extractor {
+    /// Search with the limits of `limit`, its position is replaced for every search.
+    pub fn new(limit: Go) -> Self {
+        Self {
+            limit,
+            winning: 300,
+            margin: 200,
+            max_moves: 8,
+        }
+    }
+
+    /// The score in centipawns from which a position is winning. 300 by default.
+    pub fn winning(mut self, cp: i32) -> Self {
+        self.winning = cp;
+        self
+    }
+
+    /// How much worse in centipawns the second best move must be for the best one to be the
+    /// only solution. 200 by default.
+    pub fn margin(mut self, cp: i32) -> Self {
+        self.margin = cp;
+        self
+    }
+
+    /// The most solver moves in a puzzle. 8 by default.
+    pub fn max_moves(mut self, moves: usize) -> Self {
+        self.max_moves = moves;
+        self
+    }
+
+    fn job(&self, game: &Game, moves: &[String]) -> Go {
+        let mut job = self.limit.clone().moves(moves).variant(game.variant);
+        job.fen = game.fen.clone();
+        job
+    }
+
+    /// The score of every position of the game from the side to move, the last one included.
+    pub async fn evaluate(&self, engine: &mut Engine, game: &Game) -> Result<Vec<Score>> {
+        let moves: Vec<String> = game.moves.iter().map(|m| m.uci.clone()).collect();
+        let mut pos = game.start()?;
+        let mut scores = Vec::with_capacity(moves.len() + 1);
+        for ply in 0..=moves.len() {
+            let score = if pos.is_checkmate() {
+                Score::Mate(0)
+            } else if pos.is_game_over() {
+                Score::Cp(0)
+            } else {
+                engine
+                    .go(self.job(game, &moves[..ply]))
+                    .await?
+                    .0
+                    .score
+                    .unwrap_or_default()
+            };
+            scores.push(score);
+            if let Some(mv) = moves.get(ply) {
+                play(&mut pos, mv)?;
+            }
+        }
+        Ok(scores)
+    }
+
+    /// Evaluate the game, then look for puzzles in it.
+    pub async fn extract(&self, engine: &mut Engine, game: &Game) -> Result<Vec<Puzzle>> {
+        let scores = self.evaluate(engine, game).await?;
+        self.extract_from(engine, game, &scores).await
+    }
+
+    /// Look for puzzles using known scores of every position, as from [`Extractor::evaluate`].
+    pub async fn extract_from(
+        &self,
+        engine: &mut Engine,
+        game: &Game,
+        scores: &[Score],
+    ) -> Result<Vec<Puzzle>> {
+        let moves: Vec<String> = game.moves.iter().map(|m| m.uci.clone()).collect();
+        let mut pos = game.start()?;
+        let mut puzzles = Vec::new();
+        for (ply, mv) in moves.iter().enumerate() {
+            let before = pos.clone();
+            play(&mut pos, mv)?;
+            let (Some(&was), Some(&is)) = (scores.get(ply), scores.get(ply + 1)) else {
+                break;
+            };
+            // `was` is from the side that blundered, `is` from the solver.
+            if -centipawns(was) >= self.winning || centipawns(is) < self.winning {
+                continue;
+            }
+
+            debug!(ply, mv, "looking for a puzzle after a blunder");
+            let Some((solution, themes)) = self
+                .solve(engine, game, &moves[..=ply], pos.clone())
+                .await?
+            else {
+                continue;
+            };
+            let mut moves = vec![mv.clone()];
+            moves.extend(solution);
+            puzzles.push(Puzzle {
+                fen: Fen::from_position(before, EnPassantMode::Legal).to_string(),
+                moves,
+                ply,
+                themes,
+                variant: Some(game.variant),
+            });
+        }
+        Ok(puzzles)
+    }
+
+    /// The forcing line from `pos`, reached by `played`, and its themes.
+    async fn solve(
+        &self,
+        engine: &mut Engine,
+        game: &Game,
+        played: &[String],
+        mut pos: VariantPosition,
+    ) -> Result<Option<(Vec<String>, Vec<Theme>)>> {
+        let solver = pos.turn();
+        let start = material(&pos, solver) - material(&pos, !solver);
+        let mut moves = played.to_vec();
+        let mut line = Vec::new();
+        let mut solved = 0;
+        // The position after the reply to the last solver move, to count the material in.
+        let mut settled = pos.clone();
+
+        while solved < self.max_moves && !pos.is_game_over() {
+            let (infos, _) = engine.go_multi(self.job(game, &moves), 2).await?;
+            let best = centipawns(infos[0].score.unwrap_or_default());
+            let only = match infos.get(1) {
+                Some(second) => best - centipawns(second.score.unwrap_or_default()) >= self.margin,
+                None => true,
+            };
+            let Some(mv) = infos[0].pv.first().filter(|_| only && best >= self.winning) else {
+                break;
+            };
+
+            play(&mut pos, mv)?;
+            moves.push(mv.clone());
+            line.push(mv.clone());
+            solved += 1;
+            settled = pos.clone();
+            if pos.is_game_over() {
+                break;
+            }
+
+            let (_, reply) = engine.go(self.job(game, &moves)).await?;
+            play(&mut pos, &reply.best)?;
+            moves.push(reply.best.clone());
+            line.push(reply.best);
+            settled = pos.clone();
+        }
+
+        // The line ends on a solver move, the reply is only kept for the material.
+        if line.len() % 2 == 0 {
+            line.pop();
+        }
+        if line.is_empty() {
+            return Ok(None);
+        }
+
+        let mut themes = Vec::new();
+        if settled.is_checkmate() {
+            themes.push(Theme::MateIn(solved));
+        }
+        let end = material(&settled, solver) - material(&settled, !solver);
+        if end - start >= 2 {
+            themes.push(Theme::MaterialWin);
+        }
+        Ok(Some((line, themes)))
+    }
+}
+
+/// Why a puzzle was rejected, at the index of a move in [`Puzzle::moves`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Failure {
+    /// The engine prefers another move to the one of the solution.
+    NotBest { ply: usize, best: Info },
+    /// Another move wins as well.
+    Ambiguous { ply: usize, alternative: Info },
+    /// The defender has a better reply than the one of the solution, which is `best`'s first
+    /// move.
+    BetterDefence { ply: usize, best: Info },
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotBest { ply, best } => {
+                write!(f, "ply {ply}: {} is better", describe(best))
+            }
+            Self::Ambiguous { ply, alternative } => {
+                write!(f, "ply {ply}: {} also wins", describe(alternative))
+            }
+            Self::BetterDefence { ply, best } => {
+                write!(f, "ply {ply}: {} defends better", describe(best))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Verdict {
+    pub puzzle: Puzzle,
+    /// Empty when the puzzle passed.
+    pub failures: Vec<Failure>,
+}
+
+impl Verdict {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    pub verdicts: Vec<Verdict>,
+}
+
+impl Report {
+    pub fn passed(&self) -> usize {
+        self.verdicts.iter().filter(|v| v.passed()).count()
+    }
+
+    pub fn total(&self) -> usize {
+        self.verdicts.len()
+    }
+
+    /// Write the report as CSV, one row per puzzle with its failures separated by `;`.
+    pub fn write_csv(&self, mut w: impl Write) -> Result<()> {
+        writeln!(w, "fen,moves,passed,failures")?;
+        for verdict in &self.verdicts {
+            let failures: Vec<String> = verdict.failures.iter().map(Failure::to_string).collect();
+            writeln!(
+                w,
+                "{},{},{},{}",
+                csv_field(&verdict.puzzle.fen),
+                verdict.puzzle.moves.join(" "),
+                verdict.passed(),
+                csv_field(&failures.join("; ")),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks existing puzzles with `MultiPV 2` searches along their solution.
+///
+/// At every solver move the solution must be the best move and no other move may be winning,
+/// except for the final mate where any mate will do. At every defender move no reply may be
+/// better for the defender than the stored one by `margin` or more.
+#[derive(Debug, Clone)]
+pub struct Validator {
+    limit: Go,
+    winning: i32,
+    margin: i32,
+}
+
+impl Validator {
+    /// Search with the limits of `limit`, its position is replaced for every search.
+    pub fn new(limit: Go) -> Self {
+        Self {
+            limit,
+            winning: 300,
+            margin: 200,
+        }
+    }
+
+    /// The score in centipawns from which an alternative move wins. 300 by default.
+    pub fn winning(mut self, cp: i32) -> Self {
+        self.winning = cp;
+        self
+    }
+
+    /// How much better in centipawns a defence must be to reject the stored one. 200 by
+    /// default.
+    pub fn margin(mut self, cp: i32) -> Self {
+        self.margin = cp;
+        self
+    }
+
+    pub async fn run(&self, engine: &mut Engine, puzzles: &[Puzzle]) -> Result<Report> {
+        let mut report = Report::default();
+        for puzzle in puzzles {
+            report.verdicts.push(self.validate(engine, puzzle).await?);
+        }
+        Ok(report)
+    }
+
+    /// Check one puzzle, failing only if its position or moves are not valid.
+    ///
+    /// Puzzles without a variant are played in the one of `limit`, or else of the engine.
+    pub async fn validate(&self, engine: &mut Engine, puzzle: &Puzzle) -> Result<Verdict> {
+        let moves = &puzzle.moves;
+        if moves.len() < 2 {
+            bail!("puzzle has no solution: {}", puzzle.fen);
+        }
+        let variant = puzzle
+            .variant
+            .or(self.limit.variant)
+            .unwrap_or(engine.variant());
+        let limit = self.limit.clone().variant(variant);
+        let mut pos = variant::position(variant, Some(&puzzle.fen))?;
+        play(&mut pos, &moves[0])?;
+
+        let mut failures = Vec::new();
+        // The best defence, when it is not the stored reply, to compare with the next move.
+        let mut defence: Option<Info> = None;
+        for ply in 1..moves.len() {
+            let job = limit.clone().fen(&puzzle.fen).moves(&moves[..ply]);
+            let (infos, _) = engine.go_multi(job, 2).await?;
+            let best = &infos[0];
+            let expected = &moves[ply];
+
+            if ply % 2 == 0 {
+                if best.pv.first() != Some(expected) {
+                    defence = Some(best.clone());
+                }
+            } else {
+                if let Some(defence) = defence.take() {
+                    failures.extend(self.better_defence(ply - 1, defence, best.score));
+                }
+
+                let mut after = pos.clone();
+                play(&mut after, expected)?;
+                if !after.is_checkmate() {
+                    if best.pv.first() != Some(expected) {
+                        failures.push(Failure::NotBest {
+                            ply,
+                            best: best.clone(),
+                        });
+                    } else if let Some(second) = infos.get(1)
+                        && centipawns(second.score.unwrap_or_default()) >= self.winning
+                    {
+                        failures.push(Failure::Ambiguous {
+                            ply,
+                            alternative: second.clone(),
+                        });
+                    }
+                }
+            }
+            play(&mut pos, expected)?;
+        }
+
+        // Ending on a defender move leaves no solver search to compare the defence with.
+        if let Some(defence) = defence {
+            let score = if pos.is_checkmate() {
+                Some(Score::Mate(0))
+            } else if pos.is_game_over() {
+                Some(Score::Cp(0))
+            } else {
+                let job = limit.clone().fen(&puzzle.fen).moves(moves);
+                engine.go(job).await?.0.score
+            };
+            failures.extend(self.better_defence(moves.len() - 1, defence, score));
+        }
+
+        Ok(Verdict {
+            puzzle: puzzle.clone(),
+            failures,
+        })
+    }
+    /// A failure when `defence` is better for the defender by `margin` than the stored reply
+    /// at `ply`, which leaves the solver with `score`.
+    fn better_defence(&self, ply: usize, defence: Info, score: Option<Score>) -> Option<Failure> {
+        let better = -centipawns(defence.score.unwrap_or_default())
+            <= centipawns(score.unwrap_or_default()) - self.margin;
+        better.then_some(Failure::BetterDefence { ply, best: defence })
+    }
+}
@@ -1,8 +1,24 @@
-use std::str::FromStr;
+//! Search results reported by the engine.
+//!
+//! With the `serde` feature, all types here serialize to a stable JSON schema:
+//!
+//! ```json
+//! { "info": { "depth": 20, "seldepth": 28, "multipv": 1, "score": { "cp": 35 },
+//!             "bound": null, "wdl": [92, 898, 10], "nodes": 1294562, "nps": 1210992,
+//!             "hashfull": 511, "tbhits": 0, "time": 1069, "pv": ["e2e4", "e7e5"] } }
+//! { "bestmove": { "best": "e2e4", "ponder": "e7e5" } }
+//! ```
+//!
+//! Scores are `{ "cp": 35 }` or `{ "mate": -3 }`, bounds are `"lower"` or `"upper"`. Fields
+//! the engine did not report are `null`, and missing ones deserialize to their defaults.
+
+use std::{fmt, str::FromStr};
 
 use anyhow::{Context, Result, bail};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum Score {
     Cp(i32),
     Mate(i32),
@@ -14,38 +30,53 @@ impl Default for Score {
     }
 }
 
-#[derive(Debug, Default)]
+/// Whether the score is only a bound, reported when the search failed high or low.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Bound {
+    Lower,
+    Upper,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct Info {
     /// The depth of the search, which is the number of half-moves the engine is looking ahead.
     pub depth: u32,
     /// The selective depth, which indicates the deepest point the search has reached in some lines.
-    pub seldepth: u32,
-    /// The number of principal variations (PVs) being considered. In this case, only the best move (single PV) is being reported.
-    pub multipv: u32,
+    pub seldepth: Option<u32>,
+    /// The index of this line in a MultiPV search, 1 for the best.
+    pub multipv: Option<u32>,
     /// The evaluation score of the position in centipawns (1/100th of a pawn). Positive values favor White, and negative values favor Black.
-    pub score: Score,
-    pub wdl: (u64, u64, u64),
+    pub score: Option<Score>,
+    pub bound: Option<Bound>,
+    pub wdl: Option<(u64, u64, u64)>,
     /// The number of positions (nodes) the engine has evaluated so far.
-    pub nodes: u64,
+    pub nodes: Option<u64>,
     /// Nodes per second, which indicates the speed of the engine's search.
-    pub nps: u64,
+    pub nps: Option<u64>,
     /// The percentage of the hash table used.
-    pub hashfull: u32,
+    pub hashfull: Option<u32>,
     /// The number of times a position was found in the tablebases.
-    pub tbhits: u64,
+    pub tbhits: Option<u64>,
     /// The time in milliseconds the engine has spent on this search.
-    pub time: u64,
+    pub time: Option<u64>,
     /// The principal variation, which is the sequence of moves the engine considers best from the current position.
     pub pv: Vec<String>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BestMove {
     pub best: String,
     pub ponder: Option<String>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum Search {
     Info(Info),
     BestMove(BestMove),
@@ -58,28 +89,34 @@ fn parse_info(line: &str) -> Result<Info> {
     while let Some(part) = parts.next() {
         match part {
             "depth" => info.depth = parts.next().context("no depth")?.parse()?,
-            "seldepth" => info.seldepth = parts.next().context("no seldepth")?.parse()?,
-            "multipv" => info.multipv = parts.next().context("no multipv")?.parse()?,
+            "seldepth" => info.seldepth = Some(parts.next().context("no seldepth")?.parse()?),
+            "multipv" => info.multipv = Some(parts.next().context("no multipv")?.parse()?),
             "score" => match parts.next().context("no score")? {
-                "cp" => info.score = Score::Cp(parts.next().context("no cp")?.parse()?),
-                "mate" => info.score = Score::Mate(parts.next().context("no mate")?.parse()?),
+                "cp" => info.score = Some(Score::Cp(parts.next().context("no cp")?.parse()?)),
+                "mate" => info.score = Some(Score::Mate(parts.next().context("no mate")?.parse()?)),
                 other => bail!("Unknown score: {other}"),
             },
+            "lowerbound" => info.bound = Some(Bound::Lower),
+            "upperbound" => info.bound = Some(Bound::Upper),
             "wdl" => {
-                info.wdl.0 = parts.next().context("no win %")?.parse()?;
-                info.wdl.1 = parts.next().context("no draw %")?.parse()?;
-                info.wdl.2 = parts.next().context("no lose %")?.parse()?;
+                info.wdl = Some((
+                    parts.next().context("no win %")?.parse()?,
+                    parts.next().context("no draw %")?.parse()?,
+                    parts.next().context("no lose %")?.parse()?,
+                ));
             }
-            "nodes" => info.nodes = parts.next().context("no nodes")?.parse()?,
-            "nps" => info.nps = parts.next().context("no nps")?.parse()?,
-            "hashfull" => info.hashfull = parts.next().context("no hashfull")?.parse()?,
-            "tbhits" => info.tbhits = parts.next().context("no tbhits")?.parse()?,
-            "time" => info.time = parts.next().context("no time")?.parse()?,
+            "nodes" => info.nodes = Some(parts.next().context("no nodes")?.parse()?),
+            "nps" => info.nps = Some(parts.next().context("no nps")?.parse()?),
+            "hashfull" => info.hashfull = Some(parts.next().context("no hashfull")?.parse()?),
+            "tbhits" => info.tbhits = Some(parts.next().context("no tbhits")?.parse()?),
+            "time" => info.time = Some(parts.next().context("no time")?.parse()?),
             "pv" => {
-                while let Some(mv) = parts.next() {
+                for mv in parts.by_ref() {
                     info.pv.push(mv.into());
                 }
             }
+            // The rest of the line is free text.
+            "string" => break,
             _ => (),
         };
     }
@@ -87,6 +124,75 @@ fn parse_info(line: &str) -> Result<Info> {
     Ok(info)
 }
 
+impl FromStr for Score {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut parts = s.split_whitespace();
+        let kind = parts.next().context("no score")?;
+        let value = parts
+            .next()
+            .with_context(|| format!("no {kind}"))?
+            .parse()?;
+        match kind {
+            "cp" => Ok(Self::Cp(value)),
+            "mate" => Ok(Self::Mate(value)),
+            other => bail!("Unknown score: {other}"),
+        }
+    }
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cp(cp) => write!(f, "cp {cp}"),
+            Self::Mate(mate) => write!(f, "mate {mate}"),
+        }
+    }
+}
+
+/// Formats as an `info` line, leaving out the fields that were not reported.
+///
+/// Fields are written in the order Stockfish uses, so a line parsed from an engine prints back
+/// as the same line, and parsing the output gives back an equal `Info`.
+impl fmt::Display for Info {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "info depth {}", self.depth)?;
+        if let Some(seldepth) = self.seldepth {
+            write!(f, " seldepth {seldepth}")?;
+        }
+        if let Some(multipv) = self.multipv {
+            write!(f, " multipv {multipv}")?;
+        }
+        if let Some(score) = self.score {
+            write!(f, " score {score}")?;
+        }
+        if let Some((w, d, l)) = self.wdl {
+            write!(f, " wdl {w} {d} {l}")?;
+        }
+        match self.bound {
+            Some(Bound::Lower) => write!(f, " lowerbound")?,
+            Some(Bound::Upper) => write!(f, " upperbound")?,
+            None => (),
+        }
+        for (name, value) in [
+            ("nodes", self.nodes),
+            ("nps", self.nps),
+            ("hashfull", self.hashfull.map(u64::from)),
+            ("tbhits", self.tbhits),
+            ("time", self.time),
+        ] {
+            if let Some(value) = value {
+                write!(f, " {name} {value}")?;
+            }
+        }
+        if !self.pv.is_empty() {
+            write!(f, " pv {}", self.pv.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
 impl FromStr for Info {
     type Err = anyhow::Error;
 
@@ -96,11 +202,23 @@ impl FromStr for Info {
 }
 
 fn parse_bestmove(line: &str) -> Result<BestMove> {
-    let parts = line.split_whitespace().collect::<Vec<_>>();
-    Ok(BestMove {
-        best: parts[1].into(),
-        ponder: (parts.len() > 2).then(|| parts[3].into()),
-    })
+    let mut parts = line.split_whitespace().skip(1);
+    let best = parts.next().context("no bestmove")?.into();
+    let ponder = match parts.next() {
+        Some("ponder") => Some(parts.next().context("no ponder")?.into()),
+        _ => None,
+    };
+    Ok(BestMove { best, ponder })
+}
+
+impl fmt::Display for BestMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "bestmove {}", self.best)?;
+        if let Some(ponder) = &self.ponder {
+            write!(f, " ponder {ponder}")?;
+        }
+        Ok(())
+    }
 }
 
 impl FromStr for BestMove {
@@ -110,3 +228,24 @@ impl FromStr for BestMove {
         parse_bestmove(s)
     }
 }
+
+impl FromStr for Search {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.split_whitespace().next() {
+            Some("info") => Ok(Self::Info(s.parse()?)),
+            Some("bestmove") => Ok(Self::BestMove(s.parse()?)),
+            _ => bail!("not a search line: {s}"),
+        }
+    }
+}
+
+impl fmt::Display for Search {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Info(info) => info.fmt(f),
+            Self::BestMove(best) => best.fmt(f),
+        }
+    }
+}
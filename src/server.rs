@@ -0,0 +1,455 @@
+//! The engine side of the protocol, for writing UCI engines.
+//!
+//! Implement [`EngineBackend`] and hand it to [`run_stdio`], the protocol loop takes care of
+//! the handshake, `isready`, `stop` and `ponderhit` while the backend searches on its own
+//! thread.
+
+use std::{fmt, str::FromStr};
+
+use anyhow::{Context, Result, bail};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
+    sync::{mpsc, watch},
+    task::{self, JoinHandle},
+};
+use tracing::{debug, error, trace, warn};
+
+use crate::{
+    option::UciOption,
+    search::{BestMove, Info},
+};
+
+/// The position to search, as set by the last `position` command.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Position {
+    /// `None` for the start position.
+    pub fen: Option<String>,
+    pub moves: Vec<String>,
+}
+
+/// The parameters of a `go` command.
+///
+/// Mirrors [`crate::engine::Go`] on the engine side, with every field being optional.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Go {
+    pub searchmoves: Vec<String>,
+    pub ponder: bool,
+    pub wtime: Option<u64>,
+    pub btime: Option<u64>,
+    pub winc: Option<u64>,
+    pub binc: Option<u64>,
+    pub movestogo: Option<u32>,
+    pub depth: Option<u32>,
+    pub nodes: Option<u64>,
+    pub mate: Option<u32>,
+    pub movetime: Option<u64>,
+    pub infinite: bool,
+}
+
+/// A command sent by the GUI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Command {
+    Uci,
+    Debug(bool),
+    IsReady,
+    SetOption { name: String, value: Option<String> },
+    UciNewGame,
+    Position(Position),
+    Go(Go),
+    Stop,
+    PonderHit,
+    Quit,
+}
+
+fn parse_position(mut parts: std::str::SplitWhitespace) -> Result<Position> {
+    let mut position = Position::default();
+    match parts.next().context("no position")? {
+        "startpos" => (),
+        "fen" => {
+            let fen = parts
+                .by_ref()
+                .take_while(|part| *part != "moves")
+                .collect::<Vec<_>>();
+            if fen.is_empty() {
+                bail!("no fen");
+            }
+            position.fen = Some(fen.join(" "));
+            position.moves = parts.map(Into::into).collect();
+            return Ok(position);
+        }
+        other => bail!("unknown position: {other}"),
+    }
+    match parts.next() {
+        Some("moves") => position.moves = parts.map(Into::into).collect(),
+        Some(other) => bail!("unexpected token: {other}"),
+        None => (),
+    }
+    Ok(position)
+}
+
+fn parse_go(parts: std::str::SplitWhitespace) -> Result<Go> {
+    let mut go = Go::default();
+    let mut parts = parts.peekable();
+
+    while let Some(part) = parts.next() {
+        let mut value = || parts.next().with_context(|| format!("no {part}"));
+        match part {
+            "searchmoves" => {
+                while let Some(mv) = parts.next_if(|p| !GO_KEYWORDS.contains(p)) {
+                    go.searchmoves.push(mv.into());
+                }
+            }
+            "ponder" => go.ponder = true,
+            "wtime" => go.wtime = Some(value()?.parse()?),
+            "btime" => go.btime = Some(value()?.parse()?),
+            "winc" => go.winc = Some(value()?.parse()?),
+            "binc" => go.binc = Some(value()?.parse()?),
+            "movestogo" => go.movestogo = Some(value()?.parse()?),
+            "depth" => go.depth = Some(value()?.parse()?),
+            "nodes" => go.nodes = Some(value()?.parse()?),
+            "mate" => go.mate = Some(value()?.parse()?),
+            "movetime" => go.movetime = Some(value()?.parse()?),
+            "infinite" => go.infinite = true,
+            other => bail!("unknown go parameter: {other}"),
+        }
+    }
+
+    Ok(go)
+}
+
+const GO_KEYWORDS: &[&str] = &[
+    "searchmoves",
+    "ponder",
+    "wtime",
+    "btime",
+    "winc",
+    "binc",
+    "movestogo",
+    "depth",
+    "nodes",
+    "mate",
+    "movetime",
+    "infinite",
+];
+
+fn parse_setoption(s: &str) -> Result<Command> {
+    let s = s.trim().strip_prefix("name ").context("no name")?;
+    let (name, value) = match s.split_once(" value ") {
+        Some((name, value)) => (name, Some(value.trim().into())),
+        None => (s.strip_suffix(" value").unwrap_or(s), None),
+    };
+    Ok(Command::SetOption {
+        name: name.trim().into(),
+        value,
+    })
+}
+
+fn parse_command(line: &str) -> Result<Command> {
+    let line = line.trim();
+    let mut parts = line.split_whitespace();
+    let cmd = match parts.next().context("empty command")? {
+        "uci" => Command::Uci,
+        "debug" => Command::Debug(parts.next() == Some("on")),
+        "isready" => Command::IsReady,
+        "setoption" => parse_setoption(&line["setoption".len()..])?,
+        "ucinewgame" => Command::UciNewGame,
+        "position" => Command::Position(parse_position(parts)?),
+        "go" => Command::Go(parse_go(parts)?),
+        "stop" => Command::Stop,
+        "ponderhit" => Command::PonderHit,
+        "quit" => Command::Quit,
+        other => bail!("unknown command: {other}"),
+    };
+    Ok(cmd)
+}
+
+impl FromStr for Command {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        parse_command(s)
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.fen {
+            None => write!(f, "position startpos")?,
+            Some(fen) => write!(f, "position fen {fen}")?,
+        }
+        if !self.moves.is_empty() {
+            write!(f, " moves {}", self.moves.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Go {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "go")?;
+        if !self.searchmoves.is_empty() {
+            write!(f, " searchmoves {}", self.searchmoves.join(" "))?;
+        }
+        if self.ponder {
+            write!(f, " ponder")?;
+        }
+        for (name, value) in [
+            ("wtime", self.wtime),
+            ("btime", self.btime),
+            ("winc", self.winc),
+            ("binc", self.binc),
+            ("movestogo", self.movestogo.map(u64::from)),
+            ("depth", self.depth.map(u64::from)),
+            ("nodes", self.nodes),
+            ("mate", self.mate.map(u64::from)),
+            ("movetime", self.movetime),
+        ] {
+            if let Some(value) = value {
+                write!(f, " {name} {value}")?;
+            }
+        }
+        if self.infinite {
+            write!(f, " infinite")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Signal {
+    Searching,
+    PonderHit,
+    Stop,
+}
+
+/// Handed to [`EngineBackend::search`] to report progress and check for `stop`.
+#[derive(Debug, Clone)]
+pub struct Reporter {
+    out: mpsc::UnboundedSender<String>,
+    signal: watch::Receiver<Signal>,
+    ponder: bool,
+}
+
+impl Reporter {
+    pub fn info(&self, info: &Info) {
+        _ = self.out.send(info.to_string());
+    }
+
+    /// Send an `info string` line.
+    pub fn string(&self, s: impl fmt::Display) {
+        _ = self.out.send(format!("info string {s}"));
+    }
+
+    /// Whether the GUI asked to stop, the search should return as soon as possible.
+    pub fn stopped(&self) -> bool {
+        *self.signal.borrow() == Signal::Stop
+    }
+
+    /// Whether this is a `go ponder` search still waiting for `ponderhit`.
+    pub fn pondering(&self) -> bool {
+        self.ponder && *self.signal.borrow() == Signal::Searching
+    }
+}
+
+/// An engine speaking UCI through [`run`].
+///
+/// The search runs on a blocking thread while the protocol loop keeps answering the GUI, so
+/// [`EngineBackend::search`] should poll [`Reporter::stopped`] regularly.
+pub trait EngineBackend: Send + 'static {
+    fn name(&self) -> &str;
+
+    fn author(&self) -> &str;
+
+    /// The options advertised after `uci`.
+    fn options(&self) -> Vec<UciOption> {
+        Vec::new()
+    }
+
+    fn set_option(&mut self, name: &str, value: Option<&str>) -> Result<()> {
+        _ = value;
+        bail!("unknown option: {name}")
+    }
+
+    fn new_game(&mut self) {}
+
+    fn search(&mut self, position: &Position, go: &Go, reporter: &Reporter) -> BestMove;
+}
+
+enum State<B> {
+    Idle(B),
+    Searching {
+        task: JoinHandle<Result<B>>,
+        signal: watch::Sender<Signal>,
+    },
+}
+
+impl<B: EngineBackend> State<B> {
+    /// Wait for the current search to finish, if any, and return the backend.
+    async fn idle(&mut self) -> Result<&mut B> {
+        if let State::Searching { task, .. } = self {
+            let backend = task.await.context("search panicked")??;
+            *self = State::Idle(backend);
+        }
+        match self {
+            State::Idle(backend) => Ok(backend),
+            State::Searching { .. } => unreachable!(),
+        }
+    }
+
+    /// Stop the current search, if any, and return the backend once it has finished.
+    ///
+    /// An infinite or ponder search only ends on `stop`, which the loop cannot read while
+    /// waiting for it.
+    async fn stop(&mut self) -> Result<&mut B> {
+        self.signal(Signal::Stop);
+        self.idle().await
+    }
+
+    fn signal(&self, signal: Signal) {
+        if let State::Searching { signal: tx, .. } = self {
+            tx.send_if_modified(|current| {
+                let changed = *current != Signal::Stop && *current != signal;
+                if changed {
+                    *current = signal;
+                }
+                changed
+            });
+        }
+    }
+}
+
+async fn write_lines(
+    mut output: impl AsyncWrite + Unpin,
+    mut rx: mpsc::UnboundedReceiver<String>,
+) -> Result<()> {
+    while let Some(mut line) = rx.recv().await {
+        trace!("-> {line}");
+        line.push('\n');
+        output.write_all(line.as_bytes()).await?;
+        output.flush().await?;
+    }
+    Ok(())
+}
+
+fn start<B: EngineBackend>(
+    mut backend: B,
+    position: Position,
+    go: Go,
+    out: mpsc::UnboundedSender<String>,
+) -> State<B> {
+    let (signal, signal_rx) = watch::channel(Signal::Searching);
+    let reporter = Reporter {
+        out: out.clone(),
+        signal: signal_rx.clone(),
+        ponder: go.ponder,
+    };
+
+    let task = tokio::spawn(async move {
+        let (backend, best, go) = task::spawn_blocking(move || {
+            let best = backend.search(&position, &go, &reporter);
+            (backend, best, go)
+        })
+        .await?;
+
+        // The bestmove of an infinite or ponder search is held back until the GUI allows it.
+        if go.infinite || go.ponder {
+            let mut signal = signal_rx;
+            _ = signal
+                .wait_for(|s| match s {
+                    Signal::Stop => true,
+                    Signal::PonderHit => !go.infinite,
+                    Signal::Searching => false,
+                })
+                .await;
+        }
+
+        _ = out.send(best.to_string());
+        Ok(backend)
+    });
+
+    State::Searching { task, signal }
+}
+
+/// Run the protocol loop until `quit` or the end of the input.
+///
+/// Commands that need the backend, `uci`, `setoption`, `ucinewgame` and `go`, stop the current
+/// search first.
+pub async fn run<B: EngineBackend>(
+    backend: B,
+    input: impl AsyncRead + Unpin,
+    output: impl AsyncWrite + Unpin + Send + 'static,
+) -> Result<()> {
+    let (out, out_rx) = mpsc::unbounded_channel();
+    let writer = tokio::spawn(async move {
+        if let Err(e) = write_lines(output, out_rx).await {
+            error!(cause = %e, "writer error");
+        }
+    });
+
+    let mut state = State::Idle(backend);
+    let mut position = Position::default();
+    let mut lines = BufReader::new(input).lines();
+
+    while let Some(line) = lines.next_line().await? {
+        trace!("<- {line}");
+        if line.trim().is_empty() {
+            continue;
+        }
+        let cmd = match line.parse::<Command>() {
+            Ok(cmd) => cmd,
+            Err(e) => {
+                warn!(%line, cause = %e, "invalid command");
+                continue;
+            }
+        };
+
+        match cmd {
+            Command::Uci => {
+                let backend = state.stop().await?;
+                _ = out.send(format!("id name {}", backend.name()));
+                _ = out.send(format!("id author {}", backend.author()));
+                for option in backend.options() {
+                    _ = out.send(option.to_string());
+                }
+                _ = out.send("uciok".into());
+            }
+            Command::Debug(on) => debug!(on, "debug mode"),
+            Command::IsReady => _ = out.send("readyok".into()),
+            Command::SetOption { name, value } => {
+                let backend = state.stop().await?;
+                if let Err(e) = backend.set_option(&name, value.as_deref()) {
+                    _ = out.send(format!("info string {e}"));
+                }
+            }
+            Command::UciNewGame => state.stop().await?.new_game(),
+            Command::Position(p) => position = p,
+            Command::Go(go) => {
+                state.stop().await?;
+                let State::Idle(backend) = state else {
+                    unreachable!()
+                };
+                state = start(backend, position.clone(), go, out.clone());
+            }
+            Command::Stop => state.signal(Signal::Stop),
+            Command::PonderHit => state.signal(Signal::PonderHit),
+            Command::Quit => break,
+        }
+    }
+
+    state.stop().await?;
+
+    drop(out);
+    writer.await?;
+    Ok(())
+}
+
+/// Run the protocol loop on the standard input and output of the process.
+pub async fn run_stdio<B: EngineBackend>(backend: B) -> Result<()> {
+    run(backend, tokio::io::stdin(), tokio::io::stdout()).await
+}
@@ -0,0 +1,155 @@
+use std::time::{Duration, Instant};
+#[cfg(feature = "serde")]
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+#[cfg(feature = "serde")]
+use anyhow::{Context, Result};
+#[cfg(feature = "serde")]
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+#[cfg(feature = "serde")]
+use tracing::error;
+
+use crate::{engine::Engine, mock::Script};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Direction {
+    /// A command sent to the engine.
+    Send,
+    /// A line received from the engine.
+    Recv,
+}
+
+/// A single line of a transcript, stored as one JSON object per line:
+///
+/// ```json
+/// {"t":12,"dir":"send","line":"isready"}
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Entry {
+    /// Milliseconds since the recording started.
+    pub t: u64,
+    pub dir: Direction,
+    pub line: String,
+}
+
+/// Appends every line exchanged with an engine to a JSONL file.
+///
+/// Cloning is cheap and all clones write to the same file.
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    start: Instant,
+    tx: mpsc::UnboundedSender<Entry>,
+}
+
+impl Recorder {
+    /// Create or truncate `path`. Must be called from within a tokio runtime.
+    #[cfg(feature = "serde")]
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::create(path)
+            .with_context(|| format!("failed to create transcript {}", path.display()))?;
+
+        let (tx, mut rx) = mpsc::unbounded_channel::<Entry>();
+        let mut file = tokio::fs::File::from_std(file);
+        tokio::spawn(async move {
+            while let Some(entry) = rx.recv().await {
+                let mut line = serde_json::to_string(&entry).expect("entry is serializable");
+                line.push('\n');
+                if let Err(e) = file.write_all(line.as_bytes()).await {
+                    error!(cause = %e, "transcript error");
+                    return;
+                }
+                _ = file.flush().await;
+            }
+        });
+
+        Ok(Self {
+            start: Instant::now(),
+            tx,
+        })
+    }
+
+    /// Record a line, blank lines are skipped.
+    pub fn record(&self, dir: Direction, line: &str) {
+        if line.trim().is_empty() {
+            return;
+        }
+        _ = self.tx.send(Entry {
+            t: self.start.elapsed().as_millis() as u64,
+            dir,
+            line: line.into(),
+        });
+    }
+}
+
+/// A recorded session, which can be replayed as a fake engine.
+#[derive(Debug, Default, Clone)]
+pub struct Transcript {
+    pub entries: Vec<Entry>,
+}
+
+impl Transcript {
+    #[cfg(feature = "serde")]
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .with_context(|| format!("failed to open transcript {}", path.display()))?;
+
+        let mut entries = Vec::new();
+        for (n, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry = serde_json::from_str(&line)
+                .with_context(|| format!("{}:{}", path.display(), n + 1))?;
+            entries.push(entry);
+        }
+
+        Ok(Self { entries })
+    }
+
+    /// A script expecting the recorded commands and answering with the recorded lines.
+    pub fn script(&self) -> Script {
+        self.entries
+            .iter()
+            .fold(Script::new(), |script, entry| match entry.dir {
+                Direction::Send => script.expect(&entry.line),
+                Direction::Recv => script.send(&entry.line),
+            })
+    }
+
+    /// Like [`Transcript::script`] but also reproduces the recorded delays between lines.
+    pub fn timed_script(&self) -> Script {
+        let mut script = Script::new();
+        let mut last = 0;
+        for entry in &self.entries {
+            match entry.dir {
+                Direction::Send => script = script.expect(&entry.line),
+                Direction::Recv => {
+                    if entry.t > last {
+                        script = script.delay(Duration::from_millis(entry.t - last));
+                    }
+                    script = script.send(&entry.line);
+                }
+            }
+            last = entry.t;
+        }
+        script
+    }
+
+    /// Replay the transcript as an engine.
+    ///
+    /// The returned [`crate::mock::Mock`] reports where the new session diverged.
+    pub fn replay(&self) -> (Engine, crate::mock::Mock) {
+        self.script().spawn()
+    }
+}
@@ -0,0 +1,107 @@
+//! Chess variants through the `UCI_Variant` option, as supported by Fairy-Stockfish.
+
+use anyhow::{Context, Result, bail};
+use shakmaty::{
+    CastlingMode, EnPassantMode, Move, Position,
+    fen::{Epd, Fen},
+    uci::UciMove,
+    variant::{Variant, VariantPosition},
+};
+
+use crate::{engine::Engine, option::OptionKind};
+
+/// The names engines use for a variant in their `UCI_Variant` combo, most common first.
+pub fn uci_names(variant: Variant) -> &'static [&'static str] {
+    match variant {
+        Variant::Chess => &["chess", "standard"],
+        Variant::Atomic => &["atomic"],
+        Variant::Antichess => &["antichess", "giveaway"],
+        Variant::KingOfTheHill => &["kingofthehill"],
+        Variant::ThreeCheck => &["3check", "threecheck"],
+        Variant::Crazyhouse => &["crazyhouse"],
+        Variant::RacingKings => &["racingkings"],
+        Variant::Horde => &["horde"],
+    }
+}
+
+/// The variant a `UCI_Variant` value stands for.
+pub fn from_uci_name(name: &str) -> Option<Variant> {
+    let name = name.to_lowercase();
+    Variant::ALL
+        .into_iter()
+        .find(|variant| uci_names(*variant).contains(&name.as_str()))
+}
+
+/// Parse a position of the variant, from its start position without a FEN.
+///
+/// Chess960 castling rights are accepted as well.
+pub fn position(variant: Variant, fen: Option<&str>) -> Result<VariantPosition> {
+    let Some(fen) = fen else {
+        return Ok(VariantPosition::new(variant));
+    };
+    let setup = Fen::from_ascii(fen.as_bytes())
+        .with_context(|| format!("invalid fen: {fen}"))?
+        .into_setup();
+    if let Ok(pos) = VariantPosition::from_setup(variant, setup.clone(), CastlingMode::Standard) {
+        return Ok(pos);
+    }
+    VariantPosition::from_setup(variant, setup, CastlingMode::Chess960)
+        .with_context(|| format!("invalid {} position: {fen}", variant.uci()))
+}
+
+/// Play a line of UCI moves, drops like `P@e4` included, checking each one is legal.
+pub fn line(pos: &VariantPosition, moves: &[String]) -> Result<Vec<Move>> {
+    let mut pos = pos.clone();
+    moves
+        .iter()
+        .map(|mv| {
+            let m = UciMove::from_ascii(mv.as_bytes())?
+                .to_move(&pos)
+                .with_context(|| format!("illegal move {mv}"))?;
+            pos.play_unchecked(&m);
+            Ok(m)
+        })
+        .collect()
+}
+
+/// Play a UCI move, failing if it is not legal.
+pub(crate) fn play(pos: &mut VariantPosition, mv: &str) -> Result<()> {
+    let m = UciMove::from_ascii(mv.as_bytes())?
+        .to_move(pos)
+        .with_context(|| format!("illegal move {mv}"))?;
+    pos.play_unchecked(&m);
+    Ok(())
+}
+
+/// The position reached after the moves, as a FEN without the move counters.
+pub(crate) fn normalize(variant: Variant, fen: Option<&str>, moves: &[String]) -> Result<String> {
+    let mut pos = position(variant, fen)?;
+    for m in line(&pos, moves)? {
+        pos.play_unchecked(&m);
+    }
+    Ok(Epd::from_position(pos, EnPassantMode::Legal).to_string())
+}
+
+impl Engine {
+    /// Switch the engine to another variant, using the names from its `UCI_Variant` option.
+    ///
+    /// Needs the handshake to have run, to know which variants the engine supports.
+    pub async fn set_variant(&mut self, variant: Variant) -> Result<()> {
+        let option = self
+            .supported_options()
+            .iter()
+            .find(|option| option.name.eq_ignore_ascii_case("UCI_Variant"));
+        let vars = match option.map(|option| &option.kind) {
+            Some(OptionKind::Combo { vars, .. }) => vars,
+            _ if variant == Variant::Chess => return Ok(()),
+            _ => bail!("engine does not support variants"),
+        };
+
+        let value = vars
+            .iter()
+            .find(|var| from_uci_name(var) == Some(variant))
+            .with_context(|| format!("engine does not support {}", variant.uci()))?
+            .clone();
+        self.opts(&[("UCI_Variant".to_string(), value)]).await
+    }
+}
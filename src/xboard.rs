@@ -0,0 +1,516 @@
+//! Engines that speak the XBoard protocol (CECP) instead of UCI.
+//!
+//! [`XBoard`] runs over the same transport as [`Engine`] and reports its searches with the same
+//! [`Info`] and [`BestMove`] types, so results from both protocols can be compared directly.
+//! Moves are always given and returned in UCI notation, whatever the engine prints.
+
+use std::{collections::BTreeMap, fmt::Write, time::Duration};
+
+use anyhow::{Context, Result, bail};
+use shakmaty::{
+    Move, Position,
+    san::SanPlus,
+    uci::UciMove,
+    variant::{Variant, VariantPosition},
+};
+use tokio::time::{self, Instant};
+
+use crate::{
+    engine::{Engine, EngineBuilder, Go},
+    search::{BestMove, Info, Score, Search},
+    variant,
+};
+
+/// How long to wait for `feature` lines when the engine does not send `done=0`.
+const FEATURE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long an engine without `ping` has to be quiet after `exit` before the next search.
+const QUIET: Duration = Duration::from_millis(100);
+
+/// Mate scores are `100000 - N` for a mate in `N` moves, and `-100000 + N` when mated.
+const MATE: i64 = 100_000;
+
+/// The longest mate told apart from a large score.
+const MAX_MATE: i64 = 1000;
+
+/// Features that are rejected when the engine turns them on: we always send coordinate moves
+/// and never signal the process.
+const REJECTED: [&str; 3] = ["san", "sigint", "sigterm"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Idle,
+    /// Searching after `go`, ends with a `move`.
+    Thinking,
+    /// Searching after `analyze`, only ends on `exit`.
+    Analyzing,
+}
+
+/// An XBoard engine, driven with the same searches as a UCI [`Engine`].
+///
+/// ```no_run
+/// # async fn example() -> anyhow::Result<()> {
+/// use uci::{engine::{Engine, Go}, xboard::XBoard};
+///
+/// let mut engine = XBoard::spawn(Engine::builder("crafty")).await?;
+/// let (info, best) = engine.go(Go::new().moves(&["e2e4"])).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct XBoard {
+    engine: Engine,
+    /// The features accepted during the handshake, `option` ones aside.
+    features: BTreeMap<String, String>,
+    /// The `option` features, as the engine described them.
+    options: Vec<String>,
+    /// The `level` command sent before every search without a `movetime`.
+    level: Option<String>,
+    /// The position of the current search, to read the moves the engine prints.
+    pos: VariantPosition,
+    state: State,
+    last: Option<Info>,
+    pings: u32,
+}
+
+impl XBoard {
+    /// Run the XBoard handshake on an engine connected without the UCI one.
+    pub async fn new(engine: Engine) -> Result<Self> {
+        let mut xboard = Self {
+            engine,
+            features: BTreeMap::new(),
+            options: Vec::new(),
+            level: None,
+            pos: VariantPosition::new(Variant::Chess),
+            state: State::Idle,
+            last: None,
+            pings: 0,
+        };
+        xboard.handshake().await?;
+        Ok(xboard)
+    }
+
+    /// Spawn the engine with `builder`, skipping its UCI handshake. The options of the builder
+    /// are set with `option NAME=VALUE` after the xboard handshake instead.
+    pub async fn spawn(mut builder: EngineBuilder) -> Result<Self> {
+        let options = builder.take_options();
+        let mut xboard = Self::new(builder.handshake(false).spawn().await?).await?;
+        for (name, value) in options {
+            xboard.set_option(&name, value).await?;
+        }
+        Ok(xboard)
+    }
+
+    /// Send `xboard` and `protover 2`, then accept the features until `done=1`.
+    ///
+    /// Engines that do not announce `done=0` get two seconds to send their features, as
+    /// older engines do not know about `protover` at all. Already done by [`XBoard::new`].
+    pub async fn handshake(&mut self) -> Result<()> {
+        self.engine.tx.send("xboard\nprotover 2".into()).await?;
+        self.features.clear();
+        self.options.clear();
+        let mut deadline = Some(Instant::now() + FEATURE_TIMEOUT);
+        loop {
+            let line = match deadline {
+                Some(deadline) => match time::timeout_at(deadline, self.engine.rx.recv()).await {
+                    Ok(line) => line,
+                    Err(_) => break,
+                },
+                None => self.engine.rx.recv().await,
+            };
+            let Some(line) = line else {
+                return Err(self.engine.closed("while waiting for features").await);
+            };
+            let Some(features) = line.strip_prefix("feature ") else {
+                continue;
+            };
+
+            let mut done = false;
+            let mut replies = String::new();
+            for (name, value) in parse_features(features) {
+                if REJECTED.contains(&name) && value == "1" {
+                    _ = writeln!(&mut replies, "rejected {name}");
+                    continue;
+                }
+                _ = writeln!(&mut replies, "accepted {name}");
+                match name {
+                    "done" if value == "1" => done = true,
+                    "done" => deadline = None,
+                    "option" => self.options.push(value.into()),
+                    _ => _ = self.features.insert(name.into(), value.into()),
+                }
+            }
+            self.engine.tx.send(replies.trim_end().into()).await?;
+            if done {
+                break;
+            }
+        }
+
+        // Never think on the opponent's time, searches are started explicitly.
+        self.engine.tx.send("easy".into()).await?;
+        Ok(())
+    }
+
+    /// The `myname` feature of the engine.
+    pub fn name(&self) -> Option<&str> {
+        self.feature("myname")
+    }
+
+    /// The value of a feature the engine announced, like `setboard` or `variants`.
+    pub fn feature(&self, name: &str) -> Option<&str> {
+        self.features.get(name).map(String::as_str)
+    }
+
+    fn supports(&self, feature: &str) -> bool {
+        self.feature(feature) == Some("1")
+    }
+
+    /// The options the engine announced, like `Hash -spin 64 1 4096`.
+    pub fn supported_options(&self) -> &[String] {
+        &self.options
+    }
+
+    /// Set an option announced by the engine, with `option NAME=VALUE`.
+    pub async fn set_option(&mut self, name: &str, value: impl ToString) -> Result<()> {
+        let value = value.to_string();
+        self.engine
+            .tx
+            .send(format!("option {name}={value}"))
+            .await?;
+        Ok(())
+    }
+
+    /// Play searches without a `movetime` on a clock of `base` plus `increment` per move,
+    /// with the time added back every `moves` moves, or never with `0`.
+    ///
+    /// Without a level the engine uses its own default time control.
+    pub fn level(&mut self, moves: u32, base: Duration, increment: Duration) {
+        let secs = base.as_secs();
+        let base = match secs % 60 {
+            0 => (secs / 60).to_string(),
+            s => format!("{}:{s:02}", secs / 60),
+        };
+        self.level = Some(format!("level {moves} {base} {}", increment.as_secs_f64()));
+    }
+
+    /// Send `ping` and wait for the matching `pong`, once everything sent before is handled.
+    pub async fn ping(&mut self) -> Result<()> {
+        if !self.supports("ping") {
+            bail!("engine does not support ping");
+        }
+        self.pings += 1;
+        let n = self.pings;
+        self.engine.tx.send(format!("ping {n}")).await?;
+        self.engine.wait(&format!("pong {n}")).await
+    }
+
+    /// Send `new`, waiting for the engine to reset when it supports `ping`.
+    ///
+    /// Every search starts from `new` anyway, this only matters to engines that clear their
+    /// hash tables on it.
+    pub async fn new_game(&mut self) -> Result<()> {
+        self.engine.tx.send("new".into()).await?;
+        if self.supports("ping") {
+            self.ping().await?;
+        }
+        Ok(())
+    }
+
+    /// Send `quit` and wait for the process to exit, like [`Engine::quit`].
+    pub async fn quit(self) -> Result<()> {
+        self.engine.quit().await
+    }
+
+    /// The commands that set up the position and start searching it.
+    ///
+    /// A `depth` becomes `sd` and a `movetime` becomes `st` rounded up to whole seconds, and
+    /// a search without limits runs in `analyze` mode.
+    fn prepare(&self, job: &Go) -> Result<String> {
+        if job.mate.is_some() || !job.searchmoves.is_empty() {
+            bail!("xboard has no mate or searchmoves searches");
+        }
+        let mut cmd = "new\n".to_string();
+        let variant = job.variant.unwrap_or(Variant::Chess);
+        if variant != Variant::Chess {
+            let name = self
+                .feature("variants")
+                .and_then(|variants| {
+                    let variants: Vec<&str> = variants.split(',').map(str::trim).collect();
+                    variant::uci_names(variant)
+                        .iter()
+                        .find(|name| variants.contains(name))
+                })
+                .with_context(|| format!("engine does not support {}", variant.uci()))?;
+            _ = writeln!(&mut cmd, "variant {name}");
+        }
+
+        cmd.push_str("force\n");
+        if let Some(fen) = &job.fen {
+            if !self.supports("setboard") {
+                bail!("engine does not support setboard");
+            }
+            _ = writeln!(&mut cmd, "setboard {fen}");
+        }
+        let prefix = if self.supports("usermove") {
+            "usermove "
+        } else {
+            ""
+        };
+        for mv in &job.moves {
+            _ = writeln!(&mut cmd, "{prefix}{mv}");
+        }
+
+        if job.is_infinite() {
+            cmd.push_str("post\nanalyze");
+            return Ok(cmd);
+        }
+        if let Some(depth) = job.depth {
+            _ = writeln!(&mut cmd, "sd {depth}");
+        }
+        match (job.movetime, &self.level) {
+            (Some(ms), _) => _ = writeln!(&mut cmd, "st {}", ms.div_ceil(1000).max(1)),
+            (None, Some(level)) => _ = writeln!(&mut cmd, "{level}"),
+            (None, None) => {}
+        }
+        cmd.push_str("post\ngo");
+        Ok(cmd)
+    }
+
+    /// Send the position and start searching without waiting for the result.
+    ///
+    /// Follow up with [`XBoard::recv`] until a [`Search::BestMove`] arrives.
+    pub async fn start(&mut self, job: Go) -> Result<()> {
+        let mut pos = variant::position(job.variant.unwrap_or(Variant::Chess), job.fen.as_deref())?;
+        for m in variant::line(&pos, &job.moves)? {
+            pos.play_unchecked(&m);
+        }
+        let cmd = self.prepare(&job)?;
+        self.engine.tx.send(cmd).await?;
+
+        self.pos = pos;
+        self.state = if job.is_infinite() {
+            State::Analyzing
+        } else {
+            State::Thinking
+        };
+        self.last = None;
+        Ok(())
+    }
+
+    /// Wait for the next search update, skipping any other engine output.
+    ///
+    /// Thinking output becomes an [`Info`] and the `move` an engine plays a [`BestMove`]. An
+    /// engine that resigns or claims a result instead plays `(none)`.
+    pub async fn recv(&mut self) -> Result<Search> {
+        while let Some(line) = self.engine.rx.recv().await {
+            let Some(search) = self.search(&line) else {
+                continue;
+            };
+            match &search {
+                Search::Info(info) => self.last = Some(info.clone()),
+                Search::BestMove(_) => self.state = State::Idle,
+            }
+            return Ok(search);
+        }
+        Err(self.engine.closed("during search").await)
+    }
+
+    fn search(&self, line: &str) -> Option<Search> {
+        if self.state == State::Idle {
+            return None;
+        }
+        if let Some(mv) = line.strip_prefix("move ") {
+            let mv = mv.trim();
+            let best = match read_move(&self.pos, mv) {
+                Some(m) => uci(&self.pos, &m),
+                None => mv.into(),
+            };
+            return Some(Search::BestMove(BestMove { best, ponder: None }));
+        }
+        let first = line.split_whitespace().next()?;
+        if self.state == State::Thinking
+            && (first == "resign" || ["1-0", "0-1", "1/2-1/2"].contains(&first))
+        {
+            return Some(Search::BestMove(BestMove {
+                best: "(none)".into(),
+                ponder: None,
+            }));
+        }
+        self.thinking(line).map(Search::Info)
+    }
+
+    /// Parse `ply score time nodes pv`, or the extended `ply score time nodes seldepth nps
+    /// tbhits <tab> pv`, with the time in centiseconds.
+    fn thinking(&self, line: &str) -> Option<Info> {
+        let (head, pv) = match line.split_once('\t') {
+            Some((head, pv)) => (head, Some(pv)),
+            None => (line, None),
+        };
+        let fields: Vec<&str> = head.split_whitespace().collect();
+        let count = match pv {
+            Some(_) => fields.len(),
+            None => fields.len().min(4),
+        };
+        let numbers = fields[..count]
+            .iter()
+            .map(|field| field.trim_end_matches(['.', '&']).parse::<i64>())
+            .collect::<Result<Vec<_>, _>>()
+            .ok()?;
+        if numbers.len() < 4 {
+            return None;
+        }
+        let moves = match pv {
+            Some(pv) => pv.split_whitespace().collect(),
+            None => fields[4..].to_vec(),
+        };
+
+        let count = |i: usize| numbers.get(i).and_then(|&n| u64::try_from(n).ok());
+        Some(Info {
+            depth: u32::try_from(numbers[0]).ok()?,
+            seldepth: count(4).and_then(|n| n.try_into().ok()),
+            score: Some(score(numbers[1])),
+            nodes: Some(count(3)?),
+            nps: count(5),
+            tbhits: count(6),
+            time: Some(count(2)? * 10),
+            pv: self.line(&moves),
+            ..Default::default()
+        })
+    }
+
+    /// Read a line of moves from the current position, cut at the first one that is not legal.
+    fn line(&self, moves: &[&str]) -> Vec<String> {
+        let mut pos = self.pos.clone();
+        let mut line = Vec::new();
+        for mv in moves {
+            // Some engines number their moves, `1.` or `12...`.
+            if mv.chars().all(|c| c.is_ascii_digit() || c == '.') {
+                continue;
+            }
+            let Some(m) = read_move(&pos, mv) else {
+                break;
+            };
+            line.push(uci(&pos, &m));
+            pos.play_unchecked(&m);
+        }
+        line
+    }
+
+    /// Interrupt the current search and return its result with the last `Info`, like
+    /// [`Engine::stop`].
+    ///
+    /// A search started with `go` is told to move now with `?`. An `analyze` one never plays
+    /// a move, its best move is the first one of the last main line. Nothing from the search
+    /// is left to read afterwards: with the `ping` feature the engine is pinged, without it
+    /// the output of an analysis is skipped until the engine has been quiet for a while.
+    pub async fn stop(&mut self) -> Result<Option<(Info, BestMove)>> {
+        let mut stopped = None;
+        match self.state {
+            State::Idle => {}
+            State::Thinking => {
+                self.engine.tx.send("?".into()).await?;
+                loop {
+                    if let Search::BestMove(best) = self.recv().await? {
+                        stopped = Some((self.last.take().unwrap_or_default(), best));
+                        break;
+                    }
+                }
+            }
+            State::Analyzing => {
+                self.engine.tx.send("exit".into()).await?;
+                self.state = State::Idle;
+                let info = self.last.take().unwrap_or_default();
+                let best = BestMove {
+                    best: info.pv.first().cloned().unwrap_or_else(|| "(none)".into()),
+                    ponder: info.pv.get(1).cloned(),
+                };
+                stopped = Some((info, best));
+                if !self.supports("ping") {
+                    self.drain().await?;
+                }
+            }
+        }
+
+        if self.supports("ping") {
+            self.ping().await?;
+        }
+        Ok(stopped)
+    }
+
+    /// Skip the engine output until none arrived for [`QUIET`].
+    async fn drain(&mut self) -> Result<()> {
+        loop {
+            match time::timeout(QUIET, self.engine.rx.recv()).await {
+                Ok(Some(_)) => {}
+                Ok(None) => return Err(self.engine.closed("after exit").await),
+                Err(_) => return Ok(()),
+            }
+        }
+    }
+
+    pub async fn go(&mut self, job: Go) -> Result<(Info, BestMove)> {
+        self.go_with(job, |_| {}).await
+    }
+
+    /// Like [`XBoard::go`] but calls `f` on every `Info` as it arrives.
+    pub async fn go_with(&mut self, job: Go, mut f: impl FnMut(&Info)) -> Result<(Info, BestMove)> {
+        if job.is_infinite() {
+            bail!("a search without limits only ends with stop");
+        }
+        self.start(job).await?;
+
+        let mut last: Option<Info> = None;
+        loop {
+            match self.recv().await? {
+                Search::Info(info) => {
+                    f(&info);
+                    last = Some(info);
+                }
+                Search::BestMove(best) => {
+                    let info = last.context("no info before move")?;
+                    return Ok((info, best));
+                }
+            }
+        }
+    }
+}
+
+/// Split `name=value` pairs, with string values in double quotes.
+fn parse_features(line: &str) -> Vec<(&str, &str)> {
+    let mut features = Vec::new();
+    let mut rest = line.trim_start();
+    while let Some((name, tail)) = rest.split_once('=') {
+        let (value, tail) = match tail.strip_prefix('"') {
+            Some(quoted) => quoted.split_once('"').unwrap_or((quoted, "")),
+            None => tail.split_once(char::is_whitespace).unwrap_or((tail, "")),
+        };
+        features.push((name.trim(), value));
+        rest = tail.trim_start();
+    }
+    features
+}
+
+fn score(value: i64) -> Score {
+    match value {
+        v if (MATE - MAX_MATE..MATE).contains(&v) => Score::Mate((MATE - v) as i32),
+        v if (-MATE + 1..=-MATE + MAX_MATE).contains(&v) => Score::Mate((-MATE - v) as i32),
+        v => Score::Cp(v as i32),
+    }
+}
+
+/// A move in coordinate notation or SAN, which engines use with the `san` feature.
+fn read_move(pos: &VariantPosition, mv: &str) -> Option<Move> {
+    if let Ok(uci) = UciMove::from_ascii(mv.as_bytes())
+        && let Ok(m) = uci.to_move(pos)
+    {
+        return Some(m);
+    }
+    SanPlus::from_ascii(mv.as_bytes())
+        .ok()?
+        .san
+        .to_move(pos)
+        .ok()
+}
+
+fn uci(pos: &VariantPosition, m: &Move) -> String {
+    UciMove::from_move(m, pos.castles().mode()).to_string()
+}
@@ -0,0 +1,78 @@
+use std::time::Duration;
+
+use uci::{
+    analysis::{Analysis, Update},
+    engine::Go,
+    mock::Script,
+    search::Search,
+};
+
+const DEBOUNCE: Duration = Duration::from_millis(30);
+
+fn depth(update: &Update) -> u32 {
+    match &update.search {
+        Search::Info(info) => info.depth,
+        Search::BestMove(_) => panic!("unexpected bestmove"),
+    }
+}
+
+#[tokio::test]
+async fn restarts_on_the_latest_position() {
+    let (engine, mock) = Script::new()
+        .expect("position startpos moves e2e4")
+        .expect("go infinite")
+        .send("info depth 1 score cp -30 pv e7e5")
+        .expect("stop")
+        .send("info depth 2 score cp -25 pv c7c5")
+        .send("bestmove c7c5")
+        .expect("isready")
+        .send("readyok")
+        .expect("position startpos moves e2e4 e7e5 g1f3")
+        .expect("go infinite")
+        .send("info depth 1 score cp -40 pv b8c6")
+        .expect("stop")
+        .send("bestmove b8c6")
+        .expect("isready")
+        .send("readyok")
+        .spawn();
+    let mut analysis = Analysis::start(engine, DEBOUNCE);
+
+    let first = analysis.set(Go::default().moves(&["e2e4"]));
+    let update = analysis.recv().await.unwrap();
+    assert_eq!(update.generation, first);
+    assert_eq!(depth(&update), 1);
+
+    // Only the last of rapid changes reaches the engine.
+    analysis.set(Go::default().moves(&["e2e4", "e7e5"]));
+    let last = analysis.set(Go::default().moves(&["e2e4", "e7e5", "g1f3"]));
+
+    let update = analysis.recv().await.unwrap();
+    assert_eq!(update.generation, last);
+    assert_eq!(depth(&update), 1);
+
+    analysis.finish().await.unwrap();
+    mock.finish().await.unwrap();
+}
+
+#[tokio::test]
+async fn searches_ending_on_their_own_report_bestmove() {
+    let (engine, mock) = Script::new()
+        .expect("position startpos")
+        .expect("go depth 1")
+        .send("info depth 1 score cp 20 pv e2e4")
+        .send("bestmove e2e4")
+        .spawn();
+    let mut analysis = Analysis::start(engine, DEBOUNCE);
+
+    analysis.set(Go::default().depth(1));
+    assert_eq!(depth(&analysis.recv().await.unwrap()), 1);
+    let update = analysis.recv().await.unwrap();
+    assert!(matches!(update.search, Search::BestMove(best) if best.best == "e2e4"));
+
+    let mut engine = tokio::time::timeout(Duration::from_secs(1), analysis.finish())
+        .await
+        .unwrap()
+        .unwrap();
+    mock.finish().await.unwrap();
+    assert!(engine.rx.try_recv().is_err());
+}
@@ -0,0 +1,99 @@
+use std::time::Duration;
+
+use uci::{
+    analysis::Analysis, backend::ChessEngine, engine::Go, mock::Script, pool::EnginePool,
+    search::Search, xboard::XBoard,
+};
+
+async fn reply<E: ChessEngine>(mut engine: E) -> anyhow::Result<(Option<String>, String)> {
+    engine.set_option("Hash", "32").await?;
+    engine.new_game().await?;
+    let (_, best) = engine.go(Go::new().moves(&["e2e4"])).await?;
+    let name = engine.name().map(String::from);
+    engine.quit().await?;
+    Ok((name, best.best))
+}
+
+fn xboard(script: Script) -> Script {
+    script
+        .expect("xboard")
+        .expect("protover 2")
+        .send(r#"feature ping=1 setboard=1 myname="Mock XB" done=1"#)
+        .expect("accepted ping")
+        .expect("accepted setboard")
+        .expect("accepted myname")
+        .expect("accepted done")
+        .expect("easy")
+}
+
+#[tokio::test]
+async fn same_calls_for_both_protocols() {
+    let (mut engine, mock) = Script::new()
+        .handshake()
+        .ready()
+        .expect("setoption name Hash value 32")
+        .expect("ucinewgame")
+        .ready()
+        .expect("position startpos moves e2e4")
+        .expect("go depth 10")
+        .send("info depth 10 score cp -20 pv e7e5")
+        .send("bestmove e7e5")
+        .expect("quit")
+        .spawn();
+    ChessEngine::handshake(&mut engine).await.unwrap();
+    let (name, best) = reply(engine).await.unwrap();
+    assert_eq!((name.as_deref(), best.as_str()), (Some("Mock"), "e7e5"));
+    mock.finish().await.unwrap();
+
+    let (engine, mock) = xboard(Script::new())
+        .expect("option Hash=32")
+        .expect("new")
+        .expect("ping 1")
+        .send("pong 1")
+        .expect("new")
+        .expect("force")
+        .expect("e2e4")
+        .expect("sd 10")
+        .expect("post")
+        .expect("go")
+        .send("10 -20 100 5000 e5")
+        .send("move e5")
+        .expect("quit")
+        .spawn();
+    let engine = XBoard::new(engine).await.unwrap();
+    let (name, best) = reply(engine).await.unwrap();
+    assert_eq!((name.as_deref(), best.as_str()), (Some("Mock XB"), "e7e5"));
+    mock.finish().await.unwrap();
+}
+
+#[tokio::test]
+async fn analysis_and_pool_over_xboard() {
+    let (engine, mock) = xboard(Script::new())
+        .expect("new")
+        .expect("force")
+        .expect("d2d4")
+        .expect("post")
+        .expect("analyze")
+        .send("8 25 50 9000 Nf6 c4")
+        .expect("exit")
+        .expect("ping 1")
+        .send("pong 1")
+        .spawn();
+    let engine = XBoard::new(engine).await.unwrap();
+
+    let mut analysis = Analysis::start(engine, Duration::from_millis(10));
+    let generation = analysis.set(Go::default().moves(&["d2d4"]));
+    let update = analysis.recv().await.unwrap();
+    assert_eq!(update.generation, generation);
+    let Search::Info(info) = update.search else {
+        panic!("expected an info, got {}", update.search);
+    };
+    assert_eq!(info.pv, ["g8f6", "c2c4"]);
+    let engine = analysis.finish().await.unwrap();
+
+    let pool = EnginePool::new(vec![engine]);
+    let engine = pool.get().await.unwrap();
+    assert_eq!(engine.name(), Some("Mock XB"));
+    assert_eq!(pool.idle(), 0);
+    mock.finish().await.unwrap();
+}
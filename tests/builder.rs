@@ -0,0 +1,77 @@
+#![cfg(unix)]
+
+use uci::engine::Engine;
+
+/// A tiny shell engine that answers the handshake, echoes its environment in `id name` and
+/// reports the hash size it was given before every `readyok`.
+const SCRIPT: &str = r#"
+while read -r cmd; do
+    case "$cmd" in
+        uci) echo "id name $ENGINE_NAME $1 $(basename "$PWD")"; echo uciok ;;
+        isready) echo "info string hash ${hash:-unset}"; echo readyok ;;
+        "setoption name Hash value 16") hash=16 ;;
+        quit) exit 0 ;;
+    esac
+done
+"#;
+
+#[tokio::test]
+async fn args_env_dir_and_handshake() {
+    let dir = std::env::temp_dir();
+    let mut engine = Engine::builder("sh")
+        .args(["-c", SCRIPT, "sh", "--flag"])
+        .env("ENGINE_NAME", "shell")
+        .current_dir(&dir)
+        .handshake(false)
+        .spawn()
+        .await
+        .unwrap();
+
+    engine.tx.send("uci".into()).await.unwrap();
+    let name = engine.rx.recv().await.unwrap();
+    let base = dir.file_name().unwrap().to_str().unwrap();
+    assert_eq!(name, format!("id name shell --flag {base}"));
+    engine.wait("uciok").await.unwrap();
+
+    // Without the handshake the options would never be sent.
+    let err = Engine::builder("sh")
+        .args(["-c", SCRIPT])
+        .option("Hash", 16)
+        .handshake(false)
+        .spawn()
+        .await
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("handshake"), "{err}");
+
+    let mut engine = Engine::builder("sh")
+        .args(["-c", SCRIPT])
+        .option("Hash", 16)
+        .spawn()
+        .await
+        .unwrap();
+    // The option was sent during the handshake, before its `isready`.
+    engine.tx.send("isready".into()).await.unwrap();
+    assert_eq!(engine.rx.recv().await.unwrap(), "info string hash 16");
+    engine.wait("readyok").await.unwrap();
+}
+
+#[tokio::test]
+async fn stderr_is_attached_to_errors() {
+    let mut engine = Engine::builder("sh")
+        .args(["-c", "echo 'failed to load network' >&2; exit 1"])
+        .handshake(false)
+        .spawn()
+        .await
+        .unwrap();
+
+    let err = engine.uci().await.unwrap_err().to_string();
+    assert!(err.contains("failed to load network"), "{err}");
+    assert_eq!(engine.stderr(), ["failed to load network"]);
+}
+
+#[tokio::test]
+async fn spawn_failure() {
+    let result = Engine::builder("/nonexistent/engine").spawn().await;
+    assert!(result.is_err());
+}
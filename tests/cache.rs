@@ -0,0 +1,155 @@
+mod common;
+
+use common::path;
+use uci::{
+    cache::Cache,
+    engine::{Engine, Go},
+    mock::Script,
+};
+
+async fn handshake(engine: &mut Engine) {
+    engine.uci().await.unwrap();
+    assert_eq!(engine.name(), Some("Mock"));
+}
+
+#[tokio::test]
+async fn deeper_results_answer_shallower_requests() {
+    let path = path("cache-depth");
+    let (mut engine, mock) = Script::new()
+        .handshake()
+        .expect("setoption name Threads value 4")
+        .expect("position startpos moves g1f3 g8f6 b1c3")
+        .expect("go depth 12")
+        .send("info depth 12 score cp 30 pv d7d5")
+        .send("bestmove d7d5")
+        .expect("position startpos moves b1c3 g8f6 g1f3")
+        .expect("go depth 16")
+        .send("info depth 16 score cp 25 pv d7d5 d2d4")
+        .send("bestmove d7d5 ponder d2d4")
+        .spawn();
+    handshake(&mut engine).await;
+    engine.opts(&[("Threads", "4")]).await.unwrap();
+
+    let cache = Cache::open(&path).unwrap();
+    let job = Go::default().moves(&["g1f3", "g8f6", "b1c3"]);
+    let (info, _) = cache.go(&mut engine, job.depth(12)).await.unwrap();
+    assert_eq!(info.depth, 12);
+    drop(cache);
+
+    // Same position through another move order, after a restart.
+    let cache = Cache::open(&path).unwrap();
+    let job = Go::default().moves(&["b1c3", "g8f6", "g1f3"]);
+    let (info, best) = cache.go(&mut engine, job.clone().depth(8)).await.unwrap();
+    assert_eq!(info.depth, 12);
+    assert_eq!(best.best, "d7d5");
+
+    let (info, _) = cache.go(&mut engine, job.clone().depth(16)).await.unwrap();
+    assert_eq!(info.depth, 16);
+    let (info, best) = cache.go(&mut engine, job.depth(14)).await.unwrap();
+    assert_eq!(info.depth, 16);
+    assert_eq!(best.ponder.as_deref(), Some("d2d4"));
+
+    mock.finish().await.unwrap();
+    _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn options_and_limits_are_part_of_the_key() {
+    let path = path("cache-key");
+    let (mut engine, mock) = Script::new()
+        .handshake()
+        .expect("position startpos")
+        .expect("go movetime 100")
+        .send("info depth 9 score cp 20 pv e2e4")
+        .send("bestmove e2e4")
+        .expect("position startpos")
+        .expect("go movetime 200")
+        .send("info depth 10 score cp 25 pv d2d4")
+        .send("bestmove d2d4")
+        .expect("setoption name Contempt value 50")
+        .expect("position startpos")
+        .expect("go movetime 100")
+        .send("info depth 9 score cp 60 pv c2c4")
+        .send("bestmove c2c4")
+        .spawn();
+    handshake(&mut engine).await;
+
+    let cache = Cache::open(&path).unwrap();
+    let job = Go::default().movetime(100);
+    let (_, best) = cache.go(&mut engine, job.clone()).await.unwrap();
+    assert_eq!(best.best, "e2e4");
+    let (_, best) = cache.go(&mut engine, job.clone()).await.unwrap();
+    assert_eq!(best.best, "e2e4");
+
+    let (_, best) = cache
+        .go(&mut engine, Go::default().movetime(200))
+        .await
+        .unwrap();
+    assert_eq!(best.best, "d2d4");
+
+    engine.opts(&[("Contempt", "50")]).await.unwrap();
+    let (_, best) = cache.go(&mut engine, job).await.unwrap();
+    assert_eq!(best.best, "c2c4");
+
+    mock.finish().await.unwrap();
+    _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn searches_without_a_key_bypass_the_cache() {
+    let path = path("cache-nameless");
+    let (mut engine, mock) = Script::new()
+        .expect("position startpos")
+        .expect("go depth 5")
+        .send("info depth 5 score cp 20 pv e2e4")
+        .send("bestmove e2e4")
+        .expect("position startpos")
+        .expect("go depth 5")
+        .send("info depth 5 score cp 20 pv e2e4")
+        .send("bestmove e2e4")
+        .spawn();
+
+    // Without the handshake the engine has no name to key the results with.
+    let cache = Cache::open(&path).unwrap();
+    let job = Go::default().depth(5);
+    for _ in 0..2 {
+        let (_, best) = cache.go(&mut engine, job.clone()).await.unwrap();
+        assert_eq!(best.best, "e2e4");
+    }
+    assert!(cache.get(&engine, &job).is_err());
+
+    mock.finish().await.unwrap();
+    _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn multipv_searches_leave_the_key_alone() {
+    let path = path("cache-multipv");
+    let (mut engine, mock) = Script::new()
+        .handshake()
+        .expect("position startpos")
+        .expect("go depth 8")
+        .send("info depth 8 score cp 20 pv e2e4")
+        .send("bestmove e2e4")
+        .expect("setoption name MultiPV value 2")
+        .expect("position startpos")
+        .expect("go depth 8")
+        .send("info depth 8 multipv 1 score cp 20 pv e2e4")
+        .send("info depth 8 multipv 2 score cp 15 pv d2d4")
+        .send("bestmove e2e4")
+        .expect("setoption name MultiPV value 1")
+        .spawn();
+    handshake(&mut engine).await;
+
+    let cache = Cache::open(&path).unwrap();
+    let job = Go::default().depth(8);
+    cache.go(&mut engine, job.clone()).await.unwrap();
+    let (lines, _) = engine.go_multi(job.clone(), 2).await.unwrap();
+    assert_eq!(lines.len(), 2);
+    assert!(engine.options().is_empty());
+    // Still answered from the cache.
+    assert!(cache.get(&engine, &job).unwrap().is_some());
+
+    mock.finish().await.unwrap();
+    _ = std::fs::remove_file(&path);
+}
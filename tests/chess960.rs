@@ -0,0 +1,92 @@
+use std::collections::BTreeSet;
+
+use shakmaty::Chess;
+use uci::{
+    chess960::{self, FenStyle},
+    engine::Go,
+    mock::Script,
+};
+
+const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+#[test]
+fn start_positions() {
+    assert_eq!(
+        chess960::start_fen(chess960::STANDARD, FenStyle::XFen).unwrap(),
+        START
+    );
+    assert_eq!(
+        chess960::start_fen(chess960::STANDARD, FenStyle::Shredder).unwrap(),
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1"
+    );
+    assert!(
+        chess960::start_fen(0, FenStyle::XFen)
+            .unwrap()
+            .starts_with("bbqnnrkr/")
+    );
+    assert!(
+        chess960::start_fen(959, FenStyle::XFen)
+            .unwrap()
+            .ends_with("/RKRNNQBB w KQkq - 0 1")
+    );
+    assert!(chess960::start_position(960).is_err());
+
+    let all: BTreeSet<_> = (0..960)
+        .map(|n| chess960::start_fen(n, FenStyle::Shredder).unwrap())
+        .collect();
+    assert_eq!(all.len(), 960);
+
+    let double = chess960::double_start_position(0, 959).unwrap();
+    assert_eq!(
+        chess960::fen(&double, FenStyle::Shredder),
+        "rkrnnqbb/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w HFca - 0 1"
+    );
+}
+
+#[test]
+fn shuffled_is_a_reproducible_permutation() {
+    let a = chess960::shuffled(7);
+    assert_eq!(a, chess960::shuffled(7));
+    assert_ne!(a, chess960::shuffled(8));
+    assert_eq!(a.iter().copied().collect::<BTreeSet<_>>().len(), 960);
+}
+
+#[test]
+fn castling_notation() {
+    let pos = chess960::position("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+
+    assert_eq!(chess960::to_chess960(&pos, "e1g1").unwrap(), "e1h1");
+    assert_eq!(chess960::to_chess960(&pos, "e1c1").unwrap(), "e1a1");
+    assert_eq!(chess960::to_standard(&pos, "e1h1").unwrap(), "e1g1");
+    assert_eq!(chess960::to_standard(&pos, "a1a8").unwrap(), "a1a8");
+
+    let line = ["e1h1", "e8a8"].map(String::from);
+    assert_eq!(
+        chess960::line_to_standard(&pos, &line).unwrap(),
+        ["e1g1", "e8c8"]
+    );
+    assert!(chess960::to_standard(&Chess::default(), "e1h1").is_err());
+}
+
+#[tokio::test]
+async fn engine_gets_chess960_positions() {
+    let (mut engine, mock) = Script::new()
+        .expect("setoption name UCI_Chess960 value true")
+        .expect(format!(
+            "position fen {START} moves g1f3 g8f6 e2e3 e7e6 f1e2 f8e7 e1h1"
+        ))
+        .expect("go depth 1 searchmoves e8h8 d7d5")
+        .send("info depth 1 score cp 10 pv e8h8")
+        .send("bestmove e8h8")
+        .spawn();
+    engine.opts(&[("UCI_Chess960", "true")]).await.unwrap();
+
+    let job = Go::default()
+        .fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1")
+        .moves(&["g1f3", "g8f6", "e2e3", "e7e6", "f1e2", "f8e7", "e1g1"])
+        .searchmoves(&["e8g8", "d7d5"])
+        .depth(1);
+    let (_, best) = engine.go(job).await.unwrap();
+    assert_eq!(best.best, "e8h8");
+    mock.finish().await.unwrap();
+}
@@ -0,0 +1,13 @@
+//! Fixtures shared by the integration tests.
+
+#![allow(dead_code)]
+
+/// White mates in 2 with 1. Rd8+ Rxd8 2. Rxd8#, black has no other reply.
+pub const DOUBLED_ROOKS: &str = "2r4k/6pp/8/8/8/8/3R4/3R2K1 w - - 0 1";
+
+/// A fresh database path in the temporary directory, unique to the test binary.
+pub fn path(name: &str) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("uci-{name}-{}.redb", std::process::id()));
+    _ = std::fs::remove_file(&path);
+    path
+}
@@ -0,0 +1,193 @@
+use std::time::Duration;
+
+use tokio::time::timeout;
+use uci::{
+    engine::Go,
+    mock::Script,
+    search::{Score, Search},
+};
+
+#[tokio::test]
+async fn handshake() {
+    let (mut engine, mock) = Script::new().handshake().ready().spawn();
+
+    engine.uci().await.unwrap();
+    engine.isready().await.unwrap();
+    mock.finish().await.unwrap();
+}
+
+#[tokio::test]
+async fn go_returns_last_info_and_bestmove() {
+    let (mut engine, mock) = Script::new()
+        .expect("position startpos moves e2e4")
+        .expect("go depth 2")
+        .send("info depth 1 seldepth 1 score cp 10 nodes 20 pv e7e5")
+        .send("info string some chatter")
+        .send("info depth 2 seldepth 3 score cp -15 nodes 80 pv c7c5 g1f3")
+        .send("bestmove c7c5 ponder g1f3")
+        .spawn();
+
+    let mut depths = Vec::new();
+    let job = Go::new().moves(&["e2e4"]).depth(2);
+    let (info, best) = engine
+        .go_with(job, |info| depths.push(info.depth))
+        .await
+        .unwrap();
+
+    assert_eq!(depths, [1, 2]);
+    assert_eq!(info.depth, 2);
+    assert!(matches!(info.score, Some(Score::Cp(-15))));
+    assert_eq!(info.pv, ["c7c5", "g1f3"]);
+    assert_eq!(best.best, "c7c5");
+    assert_eq!(best.ponder.as_deref(), Some("g1f3"));
+    mock.finish().await.unwrap();
+}
+
+#[tokio::test]
+async fn movetime() {
+    let (mut engine, mock) = Script::new()
+        .expect("position fen 8/8/8/8/8/8/8/K1k5 w - - 0 1")
+        .expect("go movetime 100")
+        .delay(Duration::from_millis(20))
+        .send("info depth 1 score cp 0 pv a1a2")
+        .send("bestmove a1a2")
+        .spawn();
+
+    let job = Go::default()
+        .fen("8/8/8/8/8/8/8/K1k5 w - - 0 1")
+        .movetime(100);
+    let (_, best) = engine.go(job).await.unwrap();
+
+    assert_eq!(best.best, "a1a2");
+    mock.finish().await.unwrap();
+}
+
+#[tokio::test]
+async fn malformed_lines_are_skipped() {
+    let (mut engine, mock) = Script::new()
+        .expect("position startpos")
+        .expect("go depth 10")
+        .send("info depth 1 score cp 12 pv d2d4")
+        .send("info depth two score cp 0")
+        .send("info depth 2 score banana 3")
+        .send("bestmove")
+        .send("bestmove d2d4")
+        .spawn();
+
+    let (info, best) = engine.go(Go::new()).await.unwrap();
+
+    assert_eq!(info.depth, 1);
+    assert_eq!(best.best, "d2d4");
+    mock.finish().await.unwrap();
+}
+
+#[tokio::test]
+async fn crash_during_search() {
+    let (mut engine, mock) = Script::new()
+        .expect("position startpos")
+        .expect("go depth 10")
+        .send("info depth 1 score cp 12 pv d2d4")
+        .crash()
+        .spawn();
+
+    assert!(engine.go(Go::new()).await.is_err());
+    mock.finish().await.unwrap();
+}
+
+#[tokio::test]
+async fn crash_during_handshake() {
+    let (mut engine, _mock) = Script::new().expect("uci").crash().spawn();
+
+    assert!(engine.uci().await.is_err());
+}
+
+#[tokio::test]
+async fn missing_bestmove() {
+    let (mut engine, mock) = Script::new()
+        .expect("position startpos")
+        .expect("go depth 10")
+        .send("info depth 1 score cp 12 pv d2d4")
+        .spawn();
+
+    let result = timeout(Duration::from_millis(100), engine.go(Go::new())).await;
+
+    assert!(
+        result.is_err(),
+        "search should not complete without bestmove"
+    );
+    mock.finish().await.unwrap();
+}
+
+#[tokio::test]
+async fn go_returns_the_best_line() {
+    let (mut engine, mock) = Script::new()
+        .expect("position startpos")
+        .expect("go depth 10")
+        .send("info depth 10 multipv 1 score cp 30 pv e2e4")
+        .send("info depth 10 multipv 2 score cp 20 pv d2d4")
+        .send("bestmove e2e4")
+        .spawn();
+
+    let (info, _) = engine.go(Go::new()).await.unwrap();
+    assert_eq!(info.pv, ["e2e4"]);
+    mock.finish().await.unwrap();
+}
+
+#[tokio::test]
+async fn stop_returns_the_partial_result() {
+    let (mut engine, mock) = Script::new()
+        .expect("position startpos")
+        .expect("go infinite")
+        .send("info depth 1 score cp 12 pv d2d4")
+        .expect("stop")
+        .send("info depth 2 multipv 2 score cp 5 pv e2e4")
+        .send("info depth 2 score cp 15 pv d2d4 d7d5")
+        .send("bestmove d2d4 ponder d7d5")
+        .expect("isready")
+        .send("info string late chatter")
+        .send("readyok")
+        .spawn();
+
+    engine.start(Go::default()).await.unwrap();
+    assert!(matches!(engine.recv().await.unwrap(), Search::Info(_)));
+    let (info, best) = engine.stop().await.unwrap().unwrap();
+
+    assert_eq!(info.pv, ["d2d4", "d7d5"]);
+    assert_eq!(best.best, "d2d4");
+    assert!(engine.rx.try_recv().is_err());
+    mock.finish().await.unwrap();
+}
+
+#[tokio::test]
+async fn stop_after_the_search_finished() {
+    let (mut engine, mock) = Script::new()
+        .expect("position startpos")
+        .expect("go depth 1")
+        .send("info depth 1 score cp 12 pv d2d4")
+        .send("bestmove d2d4")
+        .expect("stop")
+        .expect("isready")
+        .send("readyok")
+        .expect("isready")
+        .send("readyok")
+        .spawn();
+
+    // The bestmove is already on its way when stop is sent.
+    engine.start(Go::default().depth(1)).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    let (info, best) = engine.stop().await.unwrap().unwrap();
+    assert_eq!(info.depth, 1);
+    assert_eq!(best.best, "d2d4");
+
+    // Nothing to stop anymore.
+    assert!(engine.stop().await.unwrap().is_none());
+    mock.finish().await.unwrap();
+}
+
+#[tokio::test]
+async fn unexpected_command_fails_the_script() {
+    let (mut engine, mock) = Script::new().handshake().spawn();
+
+    assert!(engine.isready().await.is_err());
+    assert!(mock.finish().await.is_err());
+}
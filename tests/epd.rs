@@ -0,0 +1,70 @@
+use uci::{
+    engine::Go,
+    epd::{self, Epd},
+    mock::Script,
+};
+
+const WAC_001: &str =
+    r#"2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id "WAC.001"; c0 "a; b";"#;
+
+#[test]
+fn parse() {
+    let epd: Epd = WAC_001.parse().unwrap();
+
+    assert_eq!(
+        epd.fen(),
+        "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1"
+    );
+    assert_eq!(epd.id.as_deref(), Some("WAC.001"));
+    assert_eq!(epd.bm, ["Qg6"]);
+    assert_eq!(epd.best_moves().unwrap(), ["g3g6"]);
+    assert_eq!(epd.comments[&0], "a; b");
+}
+
+#[test]
+fn parse_suite_tolerates_extra_whitespace() {
+    let suite = format!(
+        "  # indented comment\n\n  2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1  w\t-   -  bm Qg6; id \"WAC.001\";\n{WAC_001}\n"
+    );
+    let suite = epd::parse_suite(&suite).unwrap();
+    assert_eq!(suite.len(), 2);
+    assert_eq!(suite[0].fen(), suite[1].fen());
+    assert_eq!(suite[0].bm, ["Qg6"]);
+}
+
+#[test]
+fn parse_rejects_lines_without_target() {
+    assert!("8/8/8/8/8/8/8/K1k5 w - - id \"x\";".parse::<Epd>().is_err());
+    assert!("8/8/8/8/8/8/8/K1k5 w - - bm Kb2".parse::<Epd>().is_err());
+}
+
+#[tokio::test]
+async fn solve_tracks_when_the_move_stayed_in_the_pv() {
+    let fen = "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1";
+    let (mut engine, mock) = Script::new()
+        .expect(format!("position fen {fen}"))
+        .expect("go depth 4")
+        .send("info depth 1 score cp 50 time 1 pv g3g6")
+        .send("info depth 2 score cp 40 time 2 pv e5f7")
+        .send("info depth 3 score cp 900 time 5 pv g3g6 f7g6")
+        .send("info depth 4 score mate 3 time 9 pv g3g6 f7g6")
+        .send("bestmove g3g6")
+        .spawn();
+
+    let suite = [WAC_001.parse().unwrap()];
+    let report = epd::run(&mut engine, &suite, &Go::default().depth(4))
+        .await
+        .unwrap();
+
+    let solution = &report.solutions[0];
+    assert!(solution.solved);
+    assert_eq!(solution.depth, Some(3));
+    assert_eq!(solution.time, Some(5));
+    assert_eq!(report.solved(), 1);
+
+    let mut csv = Vec::new();
+    report.write_csv(&mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    assert!(csv.lines().nth(1).unwrap().starts_with("WAC.001,"));
+    mock.finish().await.unwrap();
+}
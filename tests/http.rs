@@ -0,0 +1,182 @@
+#![cfg(feature = "http")]
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{Value, json};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream, connect_async, tungstenite::Message};
+use uci::{
+    http,
+    mock::{Mock, Script},
+    pool::EnginePool,
+};
+
+async fn start(script: Script) -> (String, Mock) {
+    let (engine, mock) = script.spawn();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    tokio::spawn(http::serve(listener, EnginePool::new(vec![engine])));
+    (addr, mock)
+}
+
+/// A bare HTTP/1.1 request, returning the status code and the JSON body.
+async fn request(addr: &str, method: &str, path: &str, body: &Value) -> (u16, Value) {
+    let body = body.to_string();
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let req = format!(
+        "{method} {path} HTTP/1.1\r\nHost: {addr}\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(req.as_bytes()).await.unwrap();
+
+    let mut res = String::new();
+    stream.read_to_string(&mut res).await.unwrap();
+    let (head, body) = res.split_once("\r\n\r\n").unwrap();
+    let status = head.split_whitespace().nth(1).unwrap().parse().unwrap();
+    (status, serde_json::from_str(body).unwrap())
+}
+
+#[tokio::test]
+async fn analyse() {
+    let (addr, mock) = start(
+        Script::new()
+            // Each rejected job still checks an engine out of the pool.
+            .expect("isready")
+            .send("readyok")
+            .expect("isready")
+            .send("readyok")
+            .expect("position startpos moves e2e4")
+            .expect("go depth 2")
+            .send("info depth 2 score cp -20 nodes 100 pv e7e5")
+            .send("bestmove e7e5")
+            .expect("isready")
+            .send("readyok"),
+    )
+    .await;
+
+    let (status, body) = request(&addr, "POST", "/analyse", &json!({ "moves": ["e2e4"] })).await;
+    assert_eq!(status, 400);
+    assert!(body["error"].is_string());
+
+    // Nothing but a legal position reaches the engine, which other clients share.
+    let job = json!({ "moves": ["e2e4\nsetoption name Hash value 1"], "depth": 2 });
+    let (status, body) = request(&addr, "POST", "/analyse", &job).await;
+    assert_eq!(status, 400);
+    assert!(body["error"].is_string());
+    let job = json!({ "fen": "8/8/8/8/8/8/8/8 w - - 0 1\nquit", "depth": 2 });
+    let (status, _) = request(&addr, "POST", "/analyse", &job).await;
+    assert_eq!(status, 400);
+
+    let job = json!({ "moves": ["e2e4"], "depth": 2 });
+    let (status, body) = request(&addr, "POST", "/analyse", &job).await;
+    assert_eq!(status, 200);
+    assert_eq!(body["info"]["score"], json!({ "cp": -20 }));
+    assert_eq!(body["bestmove"]["best"], "e7e5");
+    mock.finish().await.unwrap();
+
+    let (_, body) = request(&addr, "GET", "/status", &json!(null)).await;
+    assert_eq!(body, json!({ "engines": 1, "live": 1, "idle": 1 }));
+}
+
+async fn next(ws: &mut WebSocketStream<MaybeTlsStream<TcpStream>>) -> Value {
+    let Some(Ok(Message::Text(text))) = ws.next().await else {
+        panic!("websocket closed");
+    };
+    serde_json::from_str(&text).unwrap()
+}
+
+#[tokio::test]
+async fn websocket_streams_until_stopped() {
+    let (addr, mock) = start(
+        Script::new()
+            .expect("position startpos")
+            .expect("go infinite")
+            .send("info depth 1 score cp 10 pv d2d4")
+            .send("info depth 2 score cp 15 pv e2e4")
+            .expect("stop")
+            .send("bestmove e2e4")
+            .expect("isready")
+            .send("readyok")
+            .expect("isready")
+            .send("readyok")
+            .expect("isready")
+            .send("readyok"),
+    )
+    .await;
+
+    let (mut ws, _) = connect_async(format!("ws://{addr}/ws")).await.unwrap();
+    ws.send(Message::text(json!({ "go": {} }).to_string()))
+        .await
+        .unwrap();
+    let first = next(&mut ws).await;
+    assert_eq!(first["info"]["depth"], 1);
+    let second = next(&mut ws).await;
+    assert_eq!(second["info"]["depth"], 2);
+
+    ws.send(Message::text(r#""stop""#)).await.unwrap();
+    assert_eq!(next(&mut ws).await["info"]["depth"], 2);
+    assert_eq!(
+        next(&mut ws).await,
+        json!({ "bestmove": { "best": "e2e4", "ponder": null } })
+    );
+
+    ws.send(Message::text("nonsense")).await.unwrap();
+    assert!(next(&mut ws).await["error"].is_string());
+    let go = json!({ "go": { "moves": ["e2e4", "e2e4"] } });
+    ws.send(Message::text(go.to_string())).await.unwrap();
+    assert_eq!(
+        next(&mut ws).await["error"],
+        "illegal move e2e4: illegal uci"
+    );
+
+    ws.close(None).await.unwrap();
+    mock.finish().await.unwrap();
+}
+
+#[tokio::test]
+async fn websocket_waiting_for_an_engine_still_reads_requests() {
+    let (addr, mock) = start(
+        Script::new()
+            .expect("position startpos")
+            .expect("go infinite")
+            .send("info depth 1 score cp 10 pv e2e4")
+            .expect("stop")
+            .send("bestmove e2e4")
+            .expect("isready")
+            .send("readyok")
+            .expect("isready")
+            .send("readyok"),
+    )
+    .await;
+
+    let (mut busy, _) = connect_async(format!("ws://{addr}/ws")).await.unwrap();
+    busy.send(Message::text(json!({ "go": {} }).to_string()))
+        .await
+        .unwrap();
+    assert_eq!(next(&mut busy).await["info"]["depth"], 1);
+
+    // The only engine is taken, so this go waits, and the stop cancels it.
+    let (mut waiting, _) = connect_async(format!("ws://{addr}/ws")).await.unwrap();
+    waiting
+        .send(Message::text(json!({ "go": {} }).to_string()))
+        .await
+        .unwrap();
+    waiting
+        .send(Message::text(json!({ "go": {} }).to_string()))
+        .await
+        .unwrap();
+    assert_eq!(next(&mut waiting).await["error"], "already searching");
+    waiting.send(Message::text(r#""stop""#)).await.unwrap();
+    waiting.send(Message::text("nonsense")).await.unwrap();
+    assert!(next(&mut waiting).await["error"].is_string());
+    waiting.close(None).await.unwrap();
+
+    busy.send(Message::text(r#""stop""#)).await.unwrap();
+    assert_eq!(next(&mut busy).await["info"]["depth"], 1);
+    assert_eq!(next(&mut busy).await["bestmove"]["best"], "e2e4");
+    busy.close(None).await.unwrap();
+    mock.finish().await.unwrap();
+}
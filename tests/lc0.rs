@@ -0,0 +1,75 @@
+use uci::{
+    engine::Go,
+    lc0::{MoveStats, Stats},
+    mock::Script,
+    search::Score,
+};
+
+const E4: &str = "info string e2e4  (322 ) N:     234 (+ 5) (P: 12.34%) (WL:  0.05000) (D: 0.300) (M: 120.5) (Q:  0.05000) (U: 0.02300) (S:  0.07300) (V:  0.0123) ";
+const D4: &str = "info string d2d4  (293 ) N:     766 (+ 3) (P: 22.87%) (WL:  0.04150) (D: 0.415) (M: 143.2) (Q:  0.04150) (U: 0.01851) (S:  0.06001) (V:  -.----) ";
+const NODE: &str = "info string node  ( 20 ) N:    1000 (+ 8) (P: 100.00%) (WL:  0.03020) (D: 0.421) (M: 141.0) (Q:  0.03020) (V:  0.0291) ";
+
+#[test]
+fn parse_move_stats() {
+    let stats: MoveStats = E4.parse().unwrap();
+    assert_eq!(stats.mv, "e2e4");
+    assert_eq!((stats.index, stats.visits, stats.in_flight), (322, 234, 5));
+    assert_eq!(stats.policy, 12.34);
+    assert_eq!(stats.uncertainty, Some(0.023));
+    assert_eq!(stats.value, Some(0.0123));
+    let (w, d, l) = stats.wdl().unwrap();
+    assert!((w - 0.375).abs() < 1e-9 && d == 0.3 && (l - 0.325).abs() < 1e-9);
+
+    let stats: MoveStats = D4.parse().unwrap();
+    assert_eq!(stats.value, None);
+    assert!(!stats.terminal);
+
+    let mate: MoveStats = "f7f8q (1000) N: 12 (+ 0) (P: 3.00%) (Q: 1.00000) (T)"
+        .parse()
+        .unwrap();
+    assert!(mate.terminal);
+    assert_eq!(mate.wdl(), None);
+
+    assert!(
+        "info string Lc0 loaded weights"
+            .parse::<MoveStats>()
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn collects_the_last_set_of_stats() {
+    let (mut engine, mock) = Script::new()
+        .expect("position startpos")
+        .expect("go depth 10")
+        .send("info string a2a3  (204 ) N:       1 (+ 0) (P:  1.20%) (Q: -0.10000)")
+        .send(NODE)
+        .send("info depth 10 seldepth 20 multipv 1 time 500 nodes 1000 score cp 15 pv d2d4 d7d5")
+        .send("info depth 10 seldepth 18 multipv 2 time 500 nodes 1000 score cp 9 pv e2e4 e7e5")
+        .send("info string Lc0 ran out of nodes")
+        .send(E4)
+        .send(D4)
+        .send(NODE)
+        .send("bestmove d2d4 ponder d7d5")
+        .spawn();
+
+    let (info, best, stats) = engine.go_stats(Go::new()).await.unwrap();
+    assert_eq!(info.nodes, Some(1000));
+    // The second line came last, but only the best one is kept.
+    assert_eq!(info.score, Some(Score::Cp(15)));
+    assert_eq!(best.best, "d2d4");
+    assert_eq!(stats.moves.len(), 2);
+    assert_eq!(stats.node.as_ref().unwrap().visits, 1000);
+    assert_eq!(stats.most_visited().unwrap().mv, "d2d4");
+    assert_eq!(stats.get("e2e4").unwrap().visits, 234);
+
+    let shares = stats.visits_and_priors();
+    let (mv, share, prior) = shares[1];
+    assert_eq!((mv, share), ("d2d4", 0.766));
+    assert!((prior - 0.2287).abs() < 1e-9);
+
+    let mut empty = Stats::default();
+    empty.push(NODE.parse().unwrap());
+    assert!(empty.most_visited().is_none());
+    mock.finish().await.unwrap();
+}
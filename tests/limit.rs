@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+use uci::{engine::Go, limit::Limit, mock::Script, search::Score};
+
+fn stopped(script: Script) -> Script {
+    script
+        .expect("stop")
+        .send("bestmove h2h3")
+        .expect("isready")
+        .send("readyok")
+}
+
+#[tokio::test]
+async fn stops_on_mate() {
+    let (mut engine, mock) = stopped(
+        Script::new()
+            .expect("position startpos")
+            .expect("go infinite")
+            .send("info depth 1 score cp 50 pv e2e4")
+            .send("info depth 2 score mate 3 lowerbound pv d2d4")
+            .send("info depth 2 multipv 2 score mate 5 pv f2f4")
+            .send("info depth 3 score mate 2 pv h2h3"),
+    )
+    .spawn();
+
+    let (info, best) = engine
+        .go_until(Go::default(), &Limit::mate())
+        .await
+        .unwrap();
+
+    assert_eq!(info.score, Some(Score::Mate(2)));
+    assert_eq!(info.bound, None);
+    assert_eq!(best.best, "h2h3");
+    mock.finish().await.unwrap();
+}
+
+#[tokio::test]
+async fn combines_conditions() {
+    let (mut engine, mock) = stopped(
+        Script::new()
+            .expect("position startpos")
+            .expect("go infinite")
+            .send("info depth 1 score cp 10 nodes 100 pv e2e4")
+            .send("info depth 2 score cp 20 nodes 200 pv e2e4")
+            .send("info depth 3 score cp 15 nodes 300 pv d2d4")
+            .send("info depth 4 score cp 25 nodes 400 pv d2d4"),
+    )
+    .spawn();
+
+    // The best move is stable for two depths at depth 2, but not enough nodes were searched.
+    let limit = Limit::stable(2)
+        .and(Limit::nodes(400))
+        .or(Limit::score_above(100));
+    let (info, _) = engine.go_until(Go::default(), &limit).await.unwrap();
+
+    assert_eq!(info.depth, 4);
+    assert_eq!(info.pv, ["d2d4"]);
+    mock.finish().await.unwrap();
+}
+
+#[tokio::test]
+async fn stops_at_the_deadline_without_info() {
+    let (mut engine, mock) = stopped(
+        Script::new()
+            .expect("position startpos")
+            .expect("go infinite")
+            .send("info depth 1 score cp 10 pv e2e4"),
+    )
+    .spawn();
+
+    let limit = Limit::depth(30).or(Limit::time(Duration::from_millis(50)));
+    let (info, best) = engine.go_until(Go::default(), &limit).await.unwrap();
+
+    assert_eq!(info.depth, 1);
+    assert_eq!(best.best, "h2h3");
+    mock.finish().await.unwrap();
+}
+
+#[tokio::test]
+async fn searches_ending_on_their_own() {
+    let (mut engine, mock) = Script::new()
+        .expect("position startpos")
+        .expect("go depth 2")
+        .send("info depth 1 score cp 10 pv e2e4")
+        .send("info depth 2 score cp 12 pv e2e4")
+        .send("bestmove e2e4")
+        .spawn();
+
+    let job = Go::default().depth(2);
+    let (info, best) = engine.go_until(job, &Limit::mate()).await.unwrap();
+
+    assert_eq!(info.depth, 2);
+    assert_eq!(best.best, "e2e4");
+    mock.finish().await.unwrap();
+}
@@ -0,0 +1,64 @@
+mod common;
+
+use common::DOUBLED_ROOKS;
+use uci::{engine::Go, mate::Proof, mock::Script};
+
+#[tokio::test]
+async fn proves_every_defence() {
+    let (mut engine, mock) = Script::new()
+        .expect(format!("position fen {DOUBLED_ROOKS}"))
+        .expect("go mate 2")
+        .send("info depth 3 score mate 2 pv d2d8 c8d8 d1d8")
+        .send("bestmove d2d8 ponder c8d8")
+        .expect(format!("position fen {DOUBLED_ROOKS} moves d2d8 c8d8"))
+        .expect("go mate 1")
+        .send("info depth 1 score mate 1 pv d1d8")
+        .send("bestmove d1d8")
+        .spawn();
+
+    let job = Go::default().fen(DOUBLED_ROOKS);
+    let proof = engine.prove_mate(job, 2).await.unwrap().unwrap();
+    assert_eq!(
+        proof,
+        Proof {
+            mv: "d2d8".into(),
+            defences: vec![(
+                "c8d8".into(),
+                Proof {
+                    mv: "d1d8".into(),
+                    defences: Vec::new(),
+                }
+            )],
+        }
+    );
+    assert_eq!(proof.moves(), 2);
+    assert_eq!(proof.lines(), [["d2d8", "c8d8", "d1d8"]]);
+    mock.finish().await.unwrap();
+}
+
+#[tokio::test]
+async fn rejects_missing_and_false_mates() {
+    let (mut engine, mock) = Script::new()
+        .expect(format!("position fen {DOUBLED_ROOKS}"))
+        .expect("go depth 5 mate 3")
+        .send("info depth 5 score cp 900 pv d2d8 c8d8")
+        .send("bestmove d2d8")
+        .expect(format!("position fen {DOUBLED_ROOKS}"))
+        .expect("go mate 1")
+        .send("info depth 1 score mate 1 pv d2d8")
+        .send("bestmove d2d8")
+        .spawn();
+
+    let job = Go::default().fen(DOUBLED_ROOKS);
+    assert!(
+        engine
+            .go_mate(job.clone().depth(5), 3)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    // The claimed mate in 1 is only a check.
+    assert!(engine.prove_mate(job.clone(), 1).await.unwrap().is_none());
+    assert!(engine.prove_mate(job, 0).await.is_err());
+    mock.finish().await.unwrap();
+}
@@ -0,0 +1,153 @@
+mod common;
+
+use common::path;
+use uci::{
+    cache::Cache,
+    engine::Go,
+    mock::Script,
+    opening::{Results, Tree},
+    search::Score,
+};
+
+const GAMES: &str = r#"[Result "1-0"]
+
+1. e4 Nf6 2. Nf3 d6 1-0
+
+[Result "1/2-1/2"]
+
+1. Nf3 Nf6 2. e4 Nxe4 1/2-1/2
+
+[Result "*"]
+
+1. e4 e5 *
+"#;
+
+const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+const E4: &str = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1";
+const NF3: &str = "rnbqkbnr/pppppppp/8/8/8/5N2/PPPPPPPP/RNBQKB1R b KQkq - 1 1";
+/// Reached by both 1. e4 Nf6 2. Nf3 and 1. Nf3 Nf6 2. e4.
+const TRANSPOSED: &str = "rnbqkb1r/pppppppp/5n2/8/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2";
+
+#[test]
+fn counts_results_and_merges_transpositions() {
+    let mut tree = Tree::new(3);
+    assert_eq!(tree.read(GAMES.as_bytes()).unwrap(), 3);
+
+    let start = tree.get(START).unwrap();
+    let results = |games, white, draws, black| Results {
+        games,
+        white,
+        draws,
+        black,
+    };
+    assert_eq!(start.results, results(3, 1, 1, 0));
+    assert_eq!(start.moves["e2e4"].results, results(2, 1, 0, 0));
+    assert_eq!(start.moves["g1f3"].san, "Nf3");
+
+    let transposed = tree.get(TRANSPOSED).unwrap();
+    assert_eq!(
+        (transposed.ply, transposed.results),
+        (3, results(2, 1, 1, 0))
+    );
+    // Moves past the maximum ply are left out.
+    assert!(transposed.moves.is_empty());
+    let via_e4 = &tree.nodes[&tree.get(E4).unwrap().moves["g8f6"].to].moves["g1f3"];
+    let via_nf3 = &tree.nodes[&tree.get(NF3).unwrap().moves["g8f6"].to].moves["e2e4"];
+    assert_eq!(via_e4.to, via_nf3.to);
+
+    #[cfg(feature = "serde")]
+    {
+        let mut json = Vec::new();
+        tree.write_json(&mut json).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        let start = &json["rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -"];
+        assert_eq!(start["results"]["draws"], 1);
+        assert_eq!(start["moves"]["e2e4"]["san"], "e4");
+        assert!(start.get("eval").is_none());
+    }
+}
+
+#[test]
+fn counts_a_game_once_per_position_and_skips_bad_games() {
+    let games = r#"[Result "1-0"]
+
+1. Nf3 Nf6 2. Ng1 Ng8 3. Nf3 1-0
+
+[Result "0-1"]
+
+1. e4 e5 2. Ke3 0-1
+
+[Result "0-1"]
+
+1. e4 e5 0-1
+"#;
+    let mut tree = Tree::new(5);
+    assert_eq!(tree.read(games.as_bytes()).unwrap(), 2);
+
+    let start = tree.get(START).unwrap();
+    assert_eq!((start.results.games, start.results.white), (2, 1));
+    assert_eq!(start.moves["g1f3"].results.games, 1);
+    assert_eq!(tree.get(NF3).unwrap().results.games, 1);
+}
+
+#[tokio::test]
+async fn annotates_positions_through_the_cache() {
+    let path = path("opening");
+    let (mut engine, mock) = Script::new()
+        .handshake()
+        .expect(format!("position fen {START}"))
+        .expect("go depth 10")
+        .send("info depth 10 score cp 30 pv e2e4 e7e5")
+        .send("bestmove e2e4 ponder e7e5")
+        .expect(format!("position fen {E4}"))
+        .expect("go depth 10")
+        .send("info depth 10 score cp -25 pv c7c5")
+        .send("bestmove c7c5")
+        .expect(format!("position fen {NF3}"))
+        .expect("go depth 10")
+        .send("info depth 10 score cp -20 pv d7d5")
+        .send("bestmove d7d5")
+        .spawn();
+    engine.uci().await.unwrap();
+
+    let cache = Cache::open(&path).unwrap();
+    let mut tree = Tree::new(3);
+    tree.read(GAMES.as_bytes()).unwrap();
+    let limit = Go::new().depth(10);
+    assert_eq!(
+        tree.annotate(&mut engine, &cache, limit.clone(), 1)
+            .await
+            .unwrap(),
+        3
+    );
+    let eval = tree.get(E4).unwrap().eval.as_ref().unwrap();
+    assert_eq!(
+        (eval.score, &eval.pv[..]),
+        (Score::Cp(-25), &["c7c5".to_string()][..])
+    );
+    assert!(tree.get(TRANSPOSED).unwrap().eval.is_none());
+    assert_eq!(
+        tree.annotate(&mut engine, &cache, limit.clone(), 1)
+            .await
+            .unwrap(),
+        0
+    );
+
+    // A new tree over the same games is answered from the cache alone.
+    let mut again = Tree::new(3);
+    again.read(GAMES.as_bytes()).unwrap();
+    again.annotate(&mut engine, &cache, limit, 1).await.unwrap();
+    assert_eq!(again.nodes, tree.nodes);
+
+    #[cfg(feature = "serde")]
+    {
+        let mut json = Vec::new();
+        tree.write_json(&mut json).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        let start = &json["rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -"];
+        assert_eq!(start["eval"]["score"], "cp 30");
+    }
+    mock.finish().await.unwrap();
+    drop(cache);
+    _ = std::fs::remove_file(path);
+}
@@ -0,0 +1,67 @@
+use std::time::Duration;
+
+use tokio::time::{sleep, timeout};
+use uci::{engine::Go, mock::Script, pool::EnginePool};
+
+#[tokio::test]
+async fn engines_are_stopped_and_returned() {
+    let (engine, mock) = Script::new()
+        .expect("position startpos")
+        .expect("go infinite")
+        .send("info depth 1 score cp 5 pv e2e4")
+        .expect("stop")
+        .send("bestmove e2e4")
+        .expect("isready")
+        .send("readyok")
+        .spawn();
+    let pool = EnginePool::new(vec![engine]);
+
+    let mut engine = pool.get().await.unwrap();
+    assert_eq!(pool.idle(), 0);
+    engine.start(Go::default()).await.unwrap();
+    engine.recv().await.unwrap();
+
+    // A second user waits until the first one is done.
+    assert!(
+        timeout(Duration::from_millis(50), pool.get())
+            .await
+            .is_err()
+    );
+    drop(engine);
+
+    mock.finish().await.unwrap();
+    let engine = timeout(Duration::from_secs(1), pool.get()).await.unwrap();
+    assert!(engine.is_ok());
+}
+
+#[tokio::test]
+async fn dead_engines_are_dropped() {
+    let (engine, mock) = Script::new().crash().spawn();
+    let pool = EnginePool::new(vec![engine]);
+
+    drop(pool.get().await.unwrap());
+    mock.finish().await.unwrap();
+
+    sleep(Duration::from_millis(50)).await;
+    assert_eq!(pool.size(), 1);
+    assert_eq!((pool.live(), pool.idle()), (0, 0));
+    // With no engine left, waiting would never end.
+    let err = timeout(Duration::from_secs(1), pool.get())
+        .await
+        .unwrap()
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("died"), "{err}");
+}
+
+#[tokio::test]
+async fn engines_dropped_outside_a_runtime_leave_the_pool() {
+    let (engine, _mock) = Script::new().spawn();
+    let pool = EnginePool::new(vec![engine]);
+
+    let engine = pool.get().await.unwrap();
+    std::thread::spawn(move || drop(engine)).join().unwrap();
+
+    assert_eq!((pool.live(), pool.idle()), (0, 0));
+    assert!(pool.get().await.is_err());
+}
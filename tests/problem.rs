@@ -0,0 +1,124 @@
+mod common;
+
+use common::DOUBLED_ROOKS;
+use uci::{
+    engine::Go,
+    mock::Script,
+    problem::{self, Dual},
+};
+
+/// White mates in 3 with 1. g7+ Kh7 2. g8=Q+ Kh6 3. Qg6#.
+const PAWN_ENDING: &str = "7k/5K2/6P1/8/8/8/8/8 w - - 0 1";
+
+/// One search restricted to each of `candidates`, the moves from `fen` after `moves` that do
+/// not end the game at once, answering with the mate from `mates` or a plain score.
+fn searches(
+    mut script: Script,
+    fen: &str,
+    moves: &[&str],
+    n: u32,
+    candidates: &str,
+    mates: &[(&str, u32)],
+) -> Script {
+    let position = match moves {
+        [] => format!("position fen {fen}"),
+        moves => format!("position fen {fen} moves {}", moves.join(" ")),
+    };
+    for mv in candidates.split_whitespace() {
+        let score = match mates.iter().find(|(key, _)| *key == mv) {
+            Some((_, mate)) => format!("mate {mate}"),
+            None => "cp 500".into(),
+        };
+        script = script
+            .expect(position.clone())
+            .expect(format!("go mate {n} searchmoves {mv}"))
+            .send(format!("info depth 5 score {score} pv {mv}"))
+            .send(format!("bestmove {mv}"));
+    }
+    script
+}
+
+/// The first moves of [`DOUBLED_ROOKS`], none of which mates at once.
+const ROOKS_FIRST: &str = "d1a1 d1b1 d1c1 d1e1 d1f1 d2a2 d2b2 d2c2 d2e2 d2f2 d2g2 d2h2 \
+                           d2d3 d2d4 d2d5 d2d6 d2d7 d2d8 g1f1 g1h1 g1f2 g1g2 g1h2";
+
+#[tokio::test]
+async fn sound_problem_has_a_single_key() {
+    let script = searches(
+        Script::new(),
+        DOUBLED_ROOKS,
+        &[],
+        2,
+        ROOKS_FIRST,
+        &[("d2d8", 2)],
+    );
+    let (mut engine, mock) = script.spawn();
+
+    let job = Go::default().fen(DOUBLED_ROOKS);
+    let report = problem::check(&mut engine, job.clone(), 2, None)
+        .await
+        .unwrap();
+    assert_eq!(report.keys, [("d2d8".to_string(), 2)]);
+    assert!(report.is_sound());
+    // A mate takes at least one move.
+    assert!(
+        problem::check(&mut engine, job, 0, Some("d2d8"))
+            .await
+            .is_err()
+    );
+    mock.finish().await.unwrap();
+}
+
+#[tokio::test]
+async fn reports_cooks_duals_and_short_mates() {
+    let mates = [("d2d7", 3), ("d2d8", 2)];
+    let script = searches(Script::new(), DOUBLED_ROOKS, &[], 3, ROOKS_FIRST, &mates);
+    // d1d8 mates at once and is not searched.
+    let replies = "d1a1 d1b1 d1c1 d1e1 d1f1 d1d2 d1d3 d1d4 d1d5 d1d6 d1d7 \
+                   g1f1 g1h1 g1f2 g1g2 g1h2";
+    let moves = ["d2d8", "c8d8"];
+    let script = searches(script, DOUBLED_ROOKS, &moves, 2, replies, &[("d1d7", 2)]);
+    let (mut engine, mock) = script.spawn();
+
+    let job = Go::default().fen(DOUBLED_ROOKS);
+    let report = problem::check(&mut engine, job, 3, Some("d2d8"))
+        .await
+        .unwrap();
+    assert_eq!(report.cooks, ["d2d7"]);
+    assert_eq!(report.short_mates, [("d2d8".to_string(), 2)]);
+    assert_eq!(
+        report.duals,
+        [Dual {
+            line: vec!["c8d8".into()],
+            continuations: vec![("d1d7".into(), 2), ("d1d8".into(), 1)],
+        }]
+    );
+    assert!(report.refutations.is_empty());
+    assert!(!report.is_sound());
+    mock.finish().await.unwrap();
+}
+
+#[tokio::test]
+async fn follows_the_solution_to_the_mate() {
+    // f7f8 stalemates, and promoting to a bishop or a knight after 1... Kh7 as well.
+    let first = "g6g7 f7e6 f7f6 f7e7 f7e8";
+    let second = "g7g8q g7g8r f7e6 f7f6 f7e7 f7e8 f7f8";
+    let moves = ["g6g7", "h8h7"];
+
+    let script = searches(Script::new(), PAWN_ENDING, &[], 3, first, &[("g6g7", 3)]);
+    let script = searches(script, PAWN_ENDING, &moves, 2, second, &[("g7g8q", 2)]);
+    let (mut engine, mock) = script.spawn();
+    let job = Go::default().fen(PAWN_ENDING);
+    let report = problem::check(&mut engine, job, 3, None).await.unwrap();
+    assert!(report.is_sound());
+    mock.finish().await.unwrap();
+
+    // Against 2. g8=R Kh6 there is no mate in one.
+    let script = searches(Script::new(), PAWN_ENDING, &[], 3, first, &[("g6g7", 3)]);
+    let script = searches(script, PAWN_ENDING, &moves, 2, second, &[("g7g8r", 2)]);
+    let (mut engine, mock) = script.spawn();
+    let job = Go::default().fen(PAWN_ENDING);
+    let report = problem::check(&mut engine, job, 3, None).await.unwrap();
+    assert_eq!(report.refutations, [["h8h7", "g7g8r", "h7h6"]]);
+    mock.finish().await.unwrap();
+}
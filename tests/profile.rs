@@ -0,0 +1,60 @@
+#![cfg(feature = "profile")]
+
+use uci::profile::{OptionValue, Profiles};
+
+#[test]
+fn example_profiles() {
+    let profiles = Profiles::load("examples/profiles.toml").unwrap();
+
+    assert_eq!(
+        profiles.names().collect::<Vec<_>>(),
+        ["lc0-t2", "sf-16-8t", "sf-tb"]
+    );
+    let sf = profiles.get("sf-16-8t").unwrap();
+    assert_eq!(sf.options["Threads"], OptionValue::Int(8));
+    assert_eq!(sf.options["UCI_ShowWDL"], OptionValue::Bool(true));
+    assert_eq!(sf.limits.depth, Some(25));
+    assert!(profiles.get("komodo").is_err());
+}
+
+#[test]
+fn json_profiles() {
+    let profiles = Profiles::from_json(
+        r#"{ "sf": { "path": "stockfish", "options": { "SyzygyPath": "/tb" }, "limits": { "movetime": 100 } } }"#,
+    )
+    .unwrap();
+
+    let sf = profiles.get("sf").unwrap();
+    assert_eq!(sf.options["SyzygyPath"].to_string(), "/tb");
+    assert_eq!(sf.limits.movetime, Some(100));
+}
+
+#[test]
+fn unknown_fields_are_rejected() {
+    assert!(Profiles::from_toml("[sf]\npath = \"stockfish\"\nthreads = 8\n").is_err());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn spawn_runs_the_handshake_with_options() {
+    let profiles = Profiles::from_toml(
+        r#"
+        [shell]
+        path = "sh"
+        args = ["-c", '''
+            while read -r cmd; do
+                case "$cmd" in
+                    uci) echo uciok ;;
+                    "setoption name Threads value 8") ok=1 ;;
+                    isready) [ "$ok" = 1 ] && echo readyok ;;
+                esac
+            done
+        ''']
+        options = { Threads = 8 }
+        "#,
+    )
+    .unwrap();
+
+    let mut engine = profiles.spawn("shell").await.unwrap();
+    engine.isready().await.unwrap();
+}
@@ -0,0 +1,148 @@
+use tokio::io::{self, AsyncBufReadExt, AsyncWriteExt, BufReader};
+use uci::{
+    engine::Engine,
+    mock::Script,
+    proxy::{self, Log, Rewrite},
+};
+
+fn rewrite() -> Rewrite {
+    Rewrite::new()
+        .force("Threads", 8)
+        .cap("Hash", 256)
+        .rename("Proxied")
+}
+
+#[test]
+fn rewrites() {
+    let rewrite = rewrite();
+
+    assert_eq!(
+        rewrite.command("setoption name threads value 1"),
+        "setoption name threads value 8"
+    );
+    assert_eq!(
+        rewrite.command("setoption name Hash value 4096"),
+        "setoption name Hash value 256"
+    );
+    assert_eq!(
+        rewrite.command("setoption name Hash value 16"),
+        "setoption name Hash value 16"
+    );
+    assert_eq!(rewrite.command("go depth 10"), "go depth 10");
+    assert_eq!(rewrite.response("id name Stockfish 17"), "id name Proxied");
+    assert_eq!(
+        rewrite.response("option name Hash type spin default 16 min 1 max 33554432"),
+        "option name Hash type spin default 16 min 1 max 256"
+    );
+    assert_eq!(rewrite.forced(), ["setoption name Threads value 8"]);
+}
+
+#[tokio::test]
+async fn forwards_and_rewrites() {
+    let (engine, mock) = Script::new()
+        .expect("uci")
+        .send("id name Stockfish 17")
+        .send("option name Hash type spin default 16 min 1 max 33554432")
+        .send("uciok")
+        .expect("setoption name Threads value 8")
+        .expect("setoption name Hash value 256")
+        .expect("isready")
+        .send("readyok")
+        .spawn();
+
+    let (gui, proxy_io) = io::duplex(4096);
+    let (proxy_rx, proxy_tx) = io::split(proxy_io);
+    let log = Log::new(std::io::sink());
+    let proxy = tokio::spawn(proxy::run(engine, rewrite(), log, proxy_rx, proxy_tx));
+
+    let (gui_rx, mut gui_tx) = io::split(gui);
+    let mut lines = BufReader::new(gui_rx).lines();
+
+    gui_tx.write_all(b"uci\n").await.unwrap();
+    let mut received = Vec::new();
+    while let Some(line) = lines.next_line().await.unwrap() {
+        let done = line == "uciok";
+        received.push(line);
+        if done {
+            break;
+        }
+    }
+    assert_eq!(
+        received,
+        [
+            "id name Proxied",
+            "option name Hash type spin default 16 min 1 max 256",
+            "uciok"
+        ]
+    );
+
+    gui_tx
+        .write_all(b"setoption name Hash value 1024\nisready\n")
+        .await
+        .unwrap();
+    assert_eq!(lines.next_line().await.unwrap().unwrap(), "readyok");
+    mock.finish().await.unwrap();
+
+    gui_tx.shutdown().await.unwrap();
+    proxy.await.unwrap().unwrap();
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn hanging_engine_is_killed_after_quit() {
+    let engine = Engine::builder("sh")
+        .args([
+            "-c",
+            "while read -r cmd; do [ \"$cmd\" = quit ] && exec sleep 60; done",
+        ])
+        .handshake(false)
+        .spawn()
+        .await
+        .unwrap();
+
+    let (gui, proxy_io) = io::duplex(4096);
+    let (proxy_rx, proxy_tx) = io::split(proxy_io);
+    let log = Log::new(std::io::sink());
+    let proxy = tokio::spawn(proxy::run(engine, rewrite(), log, proxy_rx, proxy_tx));
+
+    let (_gui_rx, mut gui_tx) = io::split(gui);
+    gui_tx.write_all(b"quit\n").await.unwrap();
+    tokio::time::timeout(std::time::Duration::from_secs(5), proxy)
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn binary() {
+    let script = r#"
+        while read -r cmd; do
+            case "$cmd" in
+                uci) echo "id name Shell"; echo uciok ;;
+                "setoption name Threads value 2") threads=2 ;;
+                isready) [ "$threads" = 2 ] && echo readyok ;;
+            esac
+        done
+    "#;
+    let mut engine = Engine::builder(env!("CARGO_BIN_EXE_uci-proxy"))
+        .args([
+            "--rename",
+            "Proxied",
+            "--force",
+            "Threads=2",
+            "sh",
+            "-c",
+            script,
+        ])
+        .handshake(false)
+        .spawn()
+        .await
+        .unwrap();
+
+    engine.tx.send("uci".into()).await.unwrap();
+    assert_eq!(engine.rx.recv().await.unwrap(), "id name Proxied");
+    engine.wait("uciok").await.unwrap();
+    engine.isready().await.unwrap();
+}
@@ -0,0 +1,296 @@
+use shakmaty::variant::Variant;
+use uci::{
+    engine::Go,
+    mock::Script,
+    pgn,
+    puzzle::{self, Extractor, Failure, Puzzle, Theme, Validator},
+    search::Score,
+};
+
+fn position(moves: &[&str]) -> String {
+    match moves {
+        [] => "position startpos".into(),
+        moves => format!("position startpos moves {}", moves.join(" ")),
+    }
+}
+
+/// A search with a single line, as when evaluating the game or finding the best reply.
+fn search(script: Script, moves: &[&str], score: &str, best: &str) -> Script {
+    script
+        .expect(position(moves))
+        .expect("go depth 10")
+        .send(format!("info depth 10 score {score} pv {best}"))
+        .send(format!("bestmove {best}"))
+}
+
+#[tokio::test]
+async fn mate_in_one_from_known_scores() {
+    let game = &pgn::read_games(&b"1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6 4. Qxf7# 1-0"[..]).unwrap()[0];
+    let scores = [20, -20, 30, -30, 40, -50]
+        .map(Score::Cp)
+        .into_iter()
+        .chain([Score::Mate(1), Score::Mate(0)])
+        .collect::<Vec<_>>();
+
+    let moves = ["e2e4", "e7e5", "f1c4", "b8c6", "d1h5", "g8f6"];
+    let (mut engine, mock) = Script::new()
+        .expect("setoption name MultiPV value 2")
+        .expect(position(&moves))
+        .expect("go depth 10")
+        .send("info depth 10 multipv 1 score mate 1 pv h5f7")
+        .send("info depth 10 multipv 2 score cp 60 pv c4f7 e8e7")
+        .send("bestmove h5f7")
+        .expect("setoption name MultiPV value 1")
+        .spawn();
+
+    let extractor = Extractor::new(Go::new());
+    let puzzles = extractor
+        .extract_from(&mut engine, game, &scores)
+        .await
+        .unwrap();
+    assert_eq!(puzzles.len(), 1);
+    let puzzle = &puzzles[0];
+    assert_eq!(
+        puzzle.fen,
+        "r1bqkbnr/pppp1ppp/2n5/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR b KQkq - 3 3"
+    );
+    assert_eq!(
+        (puzzle.ply, puzzle.solution()),
+        (5, &["h5f7".to_string()][..])
+    );
+    assert_eq!(puzzle.themes, [Theme::MateIn(1)]);
+    assert_eq!(puzzle.variant, Some(Variant::Chess));
+
+    let mut csv = Vec::new();
+    puzzle::write_csv(&puzzles, &mut csv).unwrap();
+    assert_eq!(
+        String::from_utf8(csv).unwrap().lines().nth(1),
+        Some(
+            "r1bqkbnr/pppp1ppp/2n5/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR b KQkq - 3 3,g8f6 h5f7,5,mateIn1"
+        )
+    );
+    #[cfg(feature = "serde")]
+    {
+        let mut json = Vec::new();
+        puzzle::write_json(&puzzles, &mut json).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(json[0]["themes"][0], "mateIn1");
+        assert_eq!(json[0]["variant"], "chess");
+    }
+    mock.finish().await.unwrap();
+}
+
+#[tokio::test]
+async fn line_is_trimmed_where_it_stops_being_unique() {
+    let game = &pgn::read_games(&b"1. e4 e5 2. Qh5 Nc6 3. Qxe5+ *"[..]).unwrap()[0];
+    let moves = ["e2e4", "e7e5", "d1h5", "b8c6", "h5e5"];
+
+    let mut script = Script::new();
+    for (ply, cp) in [20, -20, 30, -30, 40].into_iter().enumerate() {
+        script = search(script, &moves[..ply], &format!("cp {cp}"), "a2a3");
+    }
+    let script = search(script, &moves, "cp 900", "c6e5")
+        .expect("setoption name MultiPV value 2")
+        .expect(position(&moves))
+        .expect("go depth 10")
+        .send("info depth 10 multipv 1 score cp 900 pv c6e5")
+        .send("info depth 10 multipv 2 score cp -100 pv f8e7")
+        .send("bestmove c6e5")
+        .expect("setoption name MultiPV value 1");
+    let after = [&moves[..], &["c6e5"]].concat();
+    let script = search(script, &after, "cp -880", "f1e2");
+    let after = [&after[..], &["f1e2"]].concat();
+    let (mut engine, mock) = script
+        .expect("setoption name MultiPV value 2")
+        .expect(position(&after))
+        .expect("go depth 10")
+        .send("info depth 10 multipv 1 score cp 800 pv d7d5")
+        .send("info depth 10 multipv 2 score cp 790 pv d7d6")
+        .send("bestmove d7d5")
+        .expect("setoption name MultiPV value 1")
+        .spawn();
+
+    let puzzles = Extractor::new(Go::new())
+        .extract(&mut engine, game)
+        .await
+        .unwrap();
+    assert_eq!(puzzles.len(), 1);
+    assert_eq!(puzzles[0].moves, ["h5e5", "c6e5"]);
+    assert_eq!(puzzles[0].themes, [Theme::MaterialWin]);
+    mock.finish().await.unwrap();
+}
+
+#[tokio::test]
+async fn extract_leaves_multipv_as_it_was_for_the_next_game() {
+    let games = pgn::read_games(&b"1. f3 e5 2. g4 *\n\n1. f3 e5 2. g4 *"[..]).unwrap();
+    let moves = ["f2f3", "e7e5", "g2g4"];
+
+    let mut script = Script::new();
+    for _ in &games {
+        for (ply, cp) in [20, 40, -30].into_iter().enumerate() {
+            script = search(script, &moves[..ply], &format!("cp {cp}"), "a2a3");
+        }
+        script = search(script, &moves, "mate 1", "d8h4")
+            .expect("setoption name MultiPV value 2")
+            .expect(position(&moves))
+            .expect("go depth 10")
+            .send("info depth 10 multipv 1 score mate 1 pv d8h4")
+            .send("info depth 10 multipv 2 score cp -200 pv b8c6")
+            .send("bestmove d8h4")
+            .expect("setoption name MultiPV value 1");
+    }
+    let (mut engine, mock) = script.spawn();
+
+    let extractor = Extractor::new(Go::new());
+    for game in &games {
+        let puzzles = extractor.extract(&mut engine, game).await.unwrap();
+        assert_eq!(puzzles.len(), 1);
+        assert_eq!(puzzles[0].moves, ["g2g4", "d8h4"]);
+    }
+    mock.finish().await.unwrap();
+}
+
+#[tokio::test]
+async fn validator_reports_every_failure() {
+    let scholar = "r1bqkbnr/pppp1ppp/2n5/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR b KQkq - 3 3";
+    let queen = "r1bqkbnr/pppp1ppp/2n5/4p2Q/4P3/8/PPPP1PPP/RNB1KBNR w KQkq - 2 3";
+    let csv = format!(
+        "fen,moves,ply,themes\n{scholar},g8f6 h5f7,5,mateIn1\n{queen},h5e5 c6e5 f1e2 d7d5\n"
+    );
+    let puzzles = puzzle::read_csv(&csv).unwrap();
+
+    let (mut engine, mock) = Script::new()
+        .expect("setoption name MultiPV value 2")
+        .expect(format!("position fen {scholar} moves g8f6"))
+        .expect("go depth 10")
+        // Any mate is fine on the last move.
+        .send("info depth 10 multipv 1 score mate 1 pv c4f7")
+        .send("info depth 10 multipv 2 score mate 1 pv h5f7")
+        .send("bestmove c4f7")
+        .expect("setoption name MultiPV value 1")
+        .expect("setoption name MultiPV value 2")
+        .expect(format!("position fen {queen} moves h5e5"))
+        .expect("go depth 10")
+        .send("info depth 10 multipv 1 score cp 900 pv c6e5 f1e2")
+        .send("info depth 10 multipv 2 score cp 350 pv f8e7")
+        .send("bestmove c6e5")
+        .expect("setoption name MultiPV value 1")
+        .expect("setoption name MultiPV value 2")
+        .expect(format!("position fen {queen} moves h5e5 c6e5"))
+        .expect("go depth 10")
+        .send("info depth 10 multipv 1 score cp -600 pv d2d4")
+        .send("info depth 10 multipv 2 score cp -880 pv f1e2")
+        .send("bestmove d2d4")
+        .expect("setoption name MultiPV value 1")
+        .expect("setoption name MultiPV value 2")
+        .expect(format!("position fen {queen} moves h5e5 c6e5 f1e2"))
+        .expect("go depth 10")
+        .send("info depth 10 multipv 1 score cp 900 pv d7d6")
+        .send("info depth 10 multipv 2 score cp 880 pv d7d5")
+        .send("bestmove d7d6")
+        .expect("setoption name MultiPV value 1")
+        .spawn();
+
+    let report = Validator::new(Go::new())
+        .run(&mut engine, &puzzles)
+        .await
+        .unwrap();
+    assert_eq!((report.passed(), report.total()), (1, 2));
+    let failures = &report.verdicts[1].failures;
+    assert!(matches!(failures[0], Failure::Ambiguous { ply: 1, .. }));
+    assert!(matches!(&failures[1], Failure::BetterDefence { ply: 2, best } if best.pv == ["d2d4"]));
+    assert!(matches!(failures[2], Failure::NotBest { ply: 3, .. }));
+
+    let mut csv = Vec::new();
+    report.write_csv(&mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    assert_eq!(
+        csv.lines().nth(2).unwrap(),
+        format!(
+            "{queen},h5e5 c6e5 f1e2 d7d5,false,\
+             ply 1: f8e7 (cp 350) also wins; ply 2: d2d4 (cp -600) defends better; \
+             ply 3: d7d6 (cp 900) is better"
+        )
+    );
+    mock.finish().await.unwrap();
+}
+
+#[tokio::test]
+async fn validator_checks_a_defence_on_the_last_move() {
+    let queen = "r1bqkbnr/pppp1ppp/2n5/4p2Q/4P3/8/PPPP1PPP/RNB1KBNR w KQkq - 2 3";
+    let csv = format!("PuzzleId,FEN,Moves,Rating\n00001,{queen},h5e5 c6e5 f1e2,1500\n");
+    let puzzles = puzzle::read_csv(&csv).unwrap();
+    assert_eq!(puzzles[0].fen, queen);
+    assert_eq!(puzzles[0].solution(), ["c6e5", "f1e2"]);
+
+    let (mut engine, mock) = Script::new()
+        .expect("setoption name MultiPV value 2")
+        .expect(format!("position fen {queen} moves h5e5"))
+        .expect("go depth 10")
+        .send("info depth 10 multipv 1 score cp 900 pv c6e5 f1e2")
+        .send("info depth 10 multipv 2 score cp -100 pv f8e7")
+        .send("bestmove c6e5")
+        .expect("setoption name MultiPV value 1")
+        .expect("setoption name MultiPV value 2")
+        .expect(format!("position fen {queen} moves h5e5 c6e5"))
+        .expect("go depth 10")
+        .send("info depth 10 multipv 1 score cp -600 pv d2d4")
+        .send("info depth 10 multipv 2 score cp -880 pv f1e2")
+        .send("bestmove d2d4")
+        .expect("setoption name MultiPV value 1")
+        .expect(format!("position fen {queen} moves h5e5 c6e5 f1e2"))
+        .expect("go depth 10")
+        .send("info depth 10 score cp 900 pv d7d5")
+        .send("bestmove d7d5")
+        .spawn();
+
+    let validator = Validator::new(Go::new());
+    let verdict = validator.validate(&mut engine, &puzzles[0]).await.unwrap();
+    assert!(matches!(
+        &verdict.failures[..],
+        [Failure::BetterDefence { ply: 2, best }] if best.pv == ["d2d4"]
+    ));
+
+    let short = Puzzle::new(queen, &["h5e5"]);
+    assert!(short.solution().is_empty());
+    assert!(validator.validate(&mut engine, &short).await.is_err());
+    mock.finish().await.unwrap();
+}
+
+#[tokio::test]
+async fn validator_plays_the_variant_of_the_puzzle() {
+    // Kingless, so only legal in antichess, where giving the last pawn away wins.
+    let fen = "8/1p6/8/8/8/8/P7/8 w - - 0 1";
+    let mut puzzle = Puzzle::new(fen, &["a2a4", "b7b5", "a4b5"]);
+    puzzle.variant = Some(Variant::Antichess);
+
+    let (mut engine, mock) = Script::new()
+        .expect("uci")
+        .send("id name Fairy-Stockfish")
+        .send("option name UCI_Variant type combo default chess var chess var giveaway")
+        .send("uciok")
+        .expect("setoption name MultiPV value 2")
+        .expect("setoption name UCI_Variant value giveaway")
+        .expect(format!("position fen {fen} moves a2a4"))
+        .expect("go depth 10")
+        .send("info depth 10 multipv 1 score mate 1 pv b7b5 a4b5")
+        .send("bestmove b7b5")
+        .expect("setoption name MultiPV value 1")
+        .expect("setoption name MultiPV value 2")
+        .expect(format!("position fen {fen} moves a2a4 b7b5"))
+        .expect("go depth 10")
+        .send("info depth 10 multipv 1 score mate -1 pv a4b5")
+        .send("bestmove a4b5")
+        .expect("setoption name MultiPV value 1")
+        .spawn();
+    engine.uci().await.unwrap();
+
+    let validator = Validator::new(Go::new());
+    let verdict = validator.validate(&mut engine, &puzzle).await.unwrap();
+    assert!(verdict.passed(), "{:?}", verdict.failures);
+    mock.finish().await.unwrap();
+
+    puzzle.variant = None;
+    let (mut engine, _) = Script::new().spawn();
+    assert!(validator.validate(&mut engine, &puzzle).await.is_err());
+}
@@ -0,0 +1,93 @@
+use proptest::prelude::*;
+use uci::search::{BestMove, Bound, Info, Score, Search};
+
+#[test]
+fn format_stockfish_lines() {
+    for line in [
+        "info depth 20 seldepth 28 multipv 1 score cp 35 wdl 92 898 10 nodes 1294562 nps 1210992 hashfull 511 tbhits 7 time 1069 pv e2e4 e7e5 g1f3",
+        "info depth 12 seldepth 14 multipv 2 score mate -3 upperbound nodes 5120 nps 512000 time 10 pv h7h6",
+        "info depth 24 seldepth 30 multipv 1 score cp 41 wdl 120 860 20 lowerbound nodes 2048000 nps 1024000 hashfull 320 tbhits 0 time 2000 pv d2d4",
+        "info depth 1 score cp 0",
+        "info depth 1 seldepth 1 multipv 1 score cp 18 nodes 20 nps 10000 hashfull 0 tbhits 0 time 2 pv e2e4",
+        "info depth 2 seldepth 0 nodes 0 time 0",
+        "bestmove e2e4 ponder e7e5",
+        "bestmove (none)",
+    ] {
+        let search: Search = line.parse().unwrap();
+        assert_eq!(search.to_string(), line);
+    }
+}
+
+#[test]
+fn parse_ignores_unknown_fields_and_strings() {
+    let info: Info =
+        "info depth 5 currmove e2e4 currmovenumber 1 score cp 10 lowerbound string pv is not a pv"
+            .parse()
+            .unwrap();
+
+    assert_eq!(info.depth, 5);
+    assert_eq!(info.score, Some(Score::Cp(10)));
+    assert_eq!(info.bound, Some(Bound::Lower));
+    assert!(info.pv.is_empty());
+}
+
+fn uci_move() -> impl Strategy<Value = String> {
+    "[a-h][1-8][a-h][1-8][qrbn]?"
+}
+
+fn score() -> impl Strategy<Value = Score> {
+    prop_oneof![
+        any::<i32>().prop_map(Score::Cp),
+        any::<i32>().prop_map(Score::Mate)
+    ]
+}
+
+prop_compose! {
+    fn info()(
+        depth in any::<u32>(),
+        seldepth in any::<Option<u32>>(),
+        multipv in any::<Option<u32>>(),
+        score in prop::option::of(score()),
+        bound in prop_oneof![Just(None), Just(Some(Bound::Lower)), Just(Some(Bound::Upper))],
+        wdl in any::<Option<(u64, u64, u64)>>(),
+        nodes in any::<Option<u64>>(),
+        nps in any::<Option<u64>>(),
+        hashfull in any::<Option<u32>>(),
+        tbhits in any::<Option<u64>>(),
+        time in any::<Option<u64>>(),
+        pv in prop::collection::vec(uci_move(), 0..8),
+    ) -> Info {
+        Info { depth, seldepth, multipv, score, bound, wdl, nodes, nps, hashfull, tbhits, time, pv }
+    }
+}
+
+prop_compose! {
+    fn bestmove()(best in uci_move(), ponder in prop::option::of(uci_move())) -> BestMove {
+        BestMove { best, ponder }
+    }
+}
+
+proptest! {
+    #[test]
+    fn score_round_trip(score in score()) {
+        prop_assert_eq!(score.to_string().parse::<Score>().unwrap(), score);
+    }
+
+    #[test]
+    fn info_round_trip(info in info()) {
+        let line = info.to_string();
+        prop_assert_eq!(line.parse::<Info>().unwrap(), info);
+    }
+
+    #[test]
+    fn bestmove_round_trip(best in bestmove()) {
+        prop_assert_eq!(best.to_string().parse::<BestMove>().unwrap(), best);
+    }
+
+    #[test]
+    fn search_round_trip(search in prop_oneof![info().prop_map(Search::Info), bestmove().prop_map(Search::BestMove)]) {
+        let line = search.to_string();
+        prop_assert_eq!(line.parse::<Search>().unwrap(), search);
+        prop_assert_eq!(line.parse::<Search>().unwrap().to_string(), line);
+    }
+}
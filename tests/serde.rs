@@ -0,0 +1,59 @@
+#![cfg(feature = "serde")]
+
+use serde_json::json;
+use uci::{
+    option::UciOption,
+    search::{BestMove, Info, Score, Search},
+};
+
+#[test]
+fn score_schema() {
+    assert_eq!(
+        serde_json::to_value(Score::Cp(35)).unwrap(),
+        json!({ "cp": 35 })
+    );
+    assert_eq!(
+        serde_json::to_value(Score::Mate(-3)).unwrap(),
+        json!({ "mate": -3 })
+    );
+}
+
+#[test]
+fn search_schema() {
+    let info: Info = "info depth 20 seldepth 28 multipv 1 score cp 35 lowerbound wdl 92 898 10 nodes 100 time 5 pv e2e4 e7e5"
+        .parse()
+        .unwrap();
+    assert_eq!(
+        serde_json::to_value(Search::Info(info.clone())).unwrap(),
+        json!({ "info": {
+            "depth": 20, "seldepth": 28, "multipv": 1, "score": { "cp": 35 }, "bound": "lower",
+            "wdl": [92, 898, 10], "nodes": 100, "nps": null, "hashfull": null, "tbhits": null,
+            "time": 5, "pv": ["e2e4", "e7e5"],
+        }})
+    );
+
+    let best: BestMove = "bestmove e2e4 ponder e7e5".parse().unwrap();
+    assert_eq!(
+        serde_json::to_value(Search::BestMove(best)).unwrap(),
+        json!({ "bestmove": { "best": "e2e4", "ponder": "e7e5" } })
+    );
+
+    let partial: Info =
+        serde_json::from_value(json!({ "depth": 3, "score": { "mate": 2 } })).unwrap();
+    assert_eq!(partial.depth, 3);
+    assert_eq!(partial.score, Some(Score::Mate(2)));
+    assert!(partial.pv.is_empty());
+}
+
+#[test]
+fn option_schema() {
+    let option: UciOption = "option name Hash type spin default 16 min 1 max 33554432"
+        .parse()
+        .unwrap();
+    let value = serde_json::to_value(&option).unwrap();
+    assert_eq!(
+        value,
+        json!({ "name": "Hash", "type": "spin", "default": 16, "min": 1, "max": 33554432 })
+    );
+    assert_eq!(serde_json::from_value::<UciOption>(value).unwrap(), option);
+}
@@ -0,0 +1,262 @@
+use std::time::Duration;
+
+use anyhow::{Result, bail};
+use tokio::{io, task::JoinHandle, time::timeout};
+use uci::{
+    engine::{Engine, Go},
+    option::{OptionKind, UciOption},
+    search::{BestMove, Info, Score, Search},
+    server::{self, Command, EngineBackend, Position, Reporter},
+};
+
+/// Searches one depth per millisecond and plays the first `searchmoves` or `e2e4`.
+#[derive(Default)]
+struct Counter {
+    contempt: i32,
+    games: u32,
+}
+
+impl EngineBackend for Counter {
+    fn name(&self) -> &str {
+        "Counter"
+    }
+
+    fn author(&self) -> &str {
+        "uci"
+    }
+
+    fn options(&self) -> Vec<UciOption> {
+        vec![UciOption::new(
+            "Contempt",
+            OptionKind::Spin {
+                default: 0,
+                min: -100,
+                max: 100,
+            },
+        )]
+    }
+
+    fn set_option(&mut self, name: &str, value: Option<&str>) -> Result<()> {
+        match (name, value) {
+            ("Contempt", Some(value)) => self.contempt = value.parse()?,
+            _ => bail!("unknown option: {name}"),
+        }
+        Ok(())
+    }
+
+    fn new_game(&mut self) {
+        self.games += 1;
+    }
+
+    fn search(&mut self, position: &Position, go: &server::Go, reporter: &Reporter) -> BestMove {
+        let best = go.searchmoves.first().cloned().unwrap_or("e2e4".into());
+        let max = go.depth.unwrap_or(u32::MAX);
+        for depth in 1..=max {
+            if reporter.stopped() {
+                break;
+            }
+            reporter.info(&Info {
+                depth,
+                score: Some(Score::Cp(self.contempt + position.moves.len() as i32)),
+                pv: vec![best.clone()],
+                ..Default::default()
+            });
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        BestMove { best, ponder: None }
+    }
+}
+
+fn connect() -> (Engine, JoinHandle<Result<()>>) {
+    let (client, server) = io::duplex(4096);
+    let (server_rx, server_tx) = io::split(server);
+    let handle = tokio::spawn(server::run(Counter::default(), server_rx, server_tx));
+
+    let (client_rx, client_tx) = io::split(client);
+    (Engine::from_io(client_rx, client_tx), handle)
+}
+
+#[test]
+fn parse_commands() {
+    let cmd: Command = "position fen 8/8/8/8/8/8/8/K1k5 w - - 0 1 moves a1a2 c1c2"
+        .parse()
+        .unwrap();
+    assert_eq!(
+        cmd,
+        Command::Position(Position {
+            fen: Some("8/8/8/8/8/8/8/K1k5 w - - 0 1".into()),
+            moves: vec!["a1a2".into(), "c1c2".into()],
+        })
+    );
+
+    let Command::Go(go) =
+        "go searchmoves e2e4 d2d4 wtime 1000 btime 900 winc 10 movestogo 20 ponder"
+            .parse()
+            .unwrap()
+    else {
+        panic!("not a go command");
+    };
+    assert_eq!(go.searchmoves, ["e2e4", "d2d4"]);
+    assert_eq!(
+        (go.wtime, go.btime, go.winc),
+        (Some(1000), Some(900), Some(10))
+    );
+    assert_eq!(go.movestogo, Some(20));
+    assert!(go.ponder && !go.infinite);
+    assert_eq!(
+        go.to_string(),
+        "go searchmoves e2e4 d2d4 ponder wtime 1000 btime 900 winc 10 movestogo 20"
+    );
+
+    assert_eq!(
+        "setoption name Skill Level value 3"
+            .parse::<Command>()
+            .unwrap(),
+        Command::SetOption {
+            name: "Skill Level".into(),
+            value: Some("3".into())
+        }
+    );
+    assert_eq!(
+        "setoption name Clear Hash".parse::<Command>().unwrap(),
+        Command::SetOption {
+            name: "Clear Hash".into(),
+            value: None
+        }
+    );
+    assert!("go depth".parse::<Command>().is_err());
+    assert!("castle".parse::<Command>().is_err());
+}
+
+#[tokio::test]
+async fn handshake_and_search() {
+    let (mut engine, handle) = connect();
+
+    engine.tx.send("uci".into()).await.unwrap();
+    let mut lines = Vec::new();
+    while let Some(line) = engine.rx.recv().await {
+        let done = line == "uciok";
+        lines.push(line);
+        if done {
+            break;
+        }
+    }
+    assert_eq!(
+        lines,
+        [
+            "id name Counter",
+            "id author uci",
+            "option name Contempt type spin default 0 min -100 max 100",
+            "uciok"
+        ]
+    );
+
+    engine.opts(&[("Contempt", "25")]).await.unwrap();
+    engine.isready().await.unwrap();
+
+    let job = Go::new().moves(&["e2e4", "e7e5"]).depth(3);
+    let (info, best) = engine.go(job).await.unwrap();
+    assert_eq!(info.depth, 3);
+    assert!(matches!(info.score, Some(Score::Cp(27))));
+    assert_eq!(best.best, "e2e4");
+
+    engine.tx.send("quit".into()).await.unwrap();
+    handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn infinite_search_waits_for_stop() {
+    let (mut engine, handle) = connect();
+
+    engine
+        .tx
+        .send("position startpos\ngo infinite searchmoves d2d4".into())
+        .await
+        .unwrap();
+    assert!(matches!(engine.recv().await.unwrap(), Search::Info(_)));
+
+    engine.tx.send("isready".into()).await.unwrap();
+    engine.wait("readyok").await.unwrap();
+
+    engine.tx.send("stop".into()).await.unwrap();
+    let best = loop {
+        if let Search::BestMove(best) = engine.recv().await.unwrap() {
+            break best;
+        }
+    };
+    assert_eq!(best.best, "d2d4");
+
+    engine.tx.send("quit".into()).await.unwrap();
+    timeout(Duration::from_secs(1), handle)
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+}
+
+#[tokio::test]
+async fn setoption_stops_an_infinite_search() {
+    let (mut engine, handle) = connect();
+
+    engine
+        .tx
+        .send("position startpos\ngo infinite".into())
+        .await
+        .unwrap();
+    assert!(matches!(engine.recv().await.unwrap(), Search::Info(_)));
+
+    engine
+        .tx
+        .send("setoption name Contempt value 10\nstop".into())
+        .await
+        .unwrap();
+    // `setoption` must not wait for the search, or the `stop` is never read.
+    let best = timeout(Duration::from_secs(1), async {
+        loop {
+            if let Search::BestMove(best) = engine.recv().await.unwrap() {
+                break best;
+            }
+        }
+    })
+    .await
+    .unwrap();
+    assert_eq!(best.best, "e2e4");
+
+    let (info, _) = engine.go(Go::new().depth(1)).await.unwrap();
+    assert!(matches!(info.score, Some(Score::Cp(10))));
+
+    engine.tx.send("quit".into()).await.unwrap();
+    timeout(Duration::from_secs(1), handle)
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+}
+
+#[tokio::test]
+async fn ponder_search_waits_for_ponderhit() {
+    let (mut engine, _handle) = connect();
+
+    engine
+        .tx
+        .send("position startpos\ngo ponder depth 2".into())
+        .await
+        .unwrap();
+    let early = timeout(Duration::from_millis(50), async {
+        loop {
+            if let Search::BestMove(_) = engine.recv().await.unwrap() {
+                break;
+            }
+        }
+    })
+    .await;
+    assert!(early.is_err(), "bestmove sent while pondering");
+
+    engine.tx.send("ponderhit".into()).await.unwrap();
+    loop {
+        if let Search::BestMove(best) = engine.recv().await.unwrap() {
+            assert_eq!(best.best, "e2e4");
+            break;
+        }
+    }
+}
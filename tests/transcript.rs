@@ -0,0 +1,81 @@
+#![cfg(feature = "serde")]
+
+use std::time::Duration;
+
+use tokio::io;
+use uci::{
+    engine::{Engine, Go},
+    mock::Script,
+    transcript::{Direction, Recorder, Transcript},
+};
+
+async fn session(engine: &mut Engine) -> String {
+    engine.uci().await.unwrap();
+    engine.isready().await.unwrap();
+    let (_, best) = engine.go(Go::new().moves(&["e2e4"])).await.unwrap();
+    best.best
+}
+
+#[tokio::test]
+async fn record_and_replay() {
+    let path = std::env::temp_dir().join(format!("uci-transcript-{}.jsonl", std::process::id()));
+
+    let mock = Script::new()
+        .handshake()
+        .ready()
+        .expect("position startpos moves e2e4")
+        .expect("go depth 10")
+        .send("info depth 10 score cp -20 pv c7c5")
+        .send("bestmove c7c5");
+    let (client, server) = io::duplex(1024);
+    let (server_rx, server_tx) = io::split(server);
+    let mock = mock.serve(server_rx, server_tx);
+
+    let (client_rx, client_tx) = io::split(client);
+    let recorder = Recorder::create(&path).unwrap();
+    let mut engine = Engine::from_io_recorded(client_rx, client_tx, recorder);
+    assert_eq!(session(&mut engine).await, "c7c5");
+    mock.finish().await.unwrap();
+    drop(engine);
+
+    // The recorder writes in the background.
+    let mut transcript = Transcript::default();
+    for _ in 0..50 {
+        transcript = Transcript::load(&path).unwrap();
+        if transcript.entries.len() == 10 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    std::fs::remove_file(&path).unwrap();
+
+    let lines = transcript
+        .entries
+        .iter()
+        .map(|e| (e.dir, e.line.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        [
+            (Direction::Send, "uci"),
+            (Direction::Recv, "id name Mock"),
+            (Direction::Recv, "id author uci"),
+            (Direction::Recv, "uciok"),
+            (Direction::Send, "isready"),
+            (Direction::Recv, "readyok"),
+            (Direction::Send, "position startpos moves e2e4"),
+            (Direction::Send, "go depth 10"),
+            (Direction::Recv, "info depth 10 score cp -20 pv c7c5"),
+            (Direction::Recv, "bestmove c7c5"),
+        ]
+    );
+
+    let (mut engine, replay) = transcript.replay();
+    assert_eq!(session(&mut engine).await, "c7c5");
+    replay.finish().await.unwrap();
+
+    let (mut engine, replay) = transcript.replay();
+    engine.uci().await.unwrap();
+    assert!(engine.go(Go::new()).await.is_err());
+    assert!(replay.finish().await.is_err());
+}
@@ -0,0 +1,67 @@
+use tokio::io;
+use uci::{engine::Engine, engine::Go, mock::Script};
+
+fn script() -> Script {
+    Script::new()
+        .handshake()
+        .ready()
+        .expect("position startpos")
+        .expect("go depth 10")
+        .send("info depth 10 score cp 31 pv e2e4 e7e5")
+        .send("bestmove e2e4 ponder e7e5")
+}
+
+async fn exercise(engine: &mut Engine) {
+    engine.uci().await.unwrap();
+    engine.isready().await.unwrap();
+    let (info, best) = engine.go(Go::new()).await.unwrap();
+    assert_eq!(info.pv, ["e2e4", "e7e5"]);
+    assert_eq!(best.best, "e2e4");
+}
+
+#[tokio::test]
+async fn tcp() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (reader, writer) = stream.into_split();
+        script().serve(reader, writer).finish().await
+    });
+
+    let mut engine = Engine::tcp(addr).await.unwrap();
+    exercise(&mut engine).await;
+    server.await.unwrap().unwrap();
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn unix() {
+    let path = std::env::temp_dir().join(format!("uci-{}.sock", std::process::id()));
+    _ = std::fs::remove_file(&path);
+    let listener = tokio::net::UnixListener::bind(&path).unwrap();
+
+    let server = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (reader, writer) = stream.into_split();
+        script().serve(reader, writer).finish().await
+    });
+
+    let mut engine = Engine::unix(&path).await.unwrap();
+    exercise(&mut engine).await;
+    server.await.unwrap().unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn in_process() {
+    let (client, server) = io::duplex(1024);
+    let (server_rx, server_tx) = io::split(server);
+    let mock = script().serve(server_rx, server_tx);
+
+    let (client_rx, client_tx) = io::split(client);
+    let mut engine = Engine::from_io(client_rx, client_tx);
+    exercise(&mut engine).await;
+    mock.finish().await.unwrap();
+}
@@ -0,0 +1,103 @@
+use shakmaty::variant::Variant;
+use uci::{
+    engine::Go,
+    mock::Script,
+    pgn,
+    search::{BestMove, Info},
+    variant,
+};
+
+const VARIANTS: &str = "option name UCI_Variant type combo default chess var chess var giveaway var 3check var crazyhouse";
+
+#[tokio::test]
+async fn selects_the_variant_from_the_engine_names() {
+    let (mut engine, mock) = Script::new()
+        .expect("uci")
+        .send("id name Fairy-Stockfish")
+        .send(VARIANTS)
+        .send("uciok")
+        .expect("setoption name UCI_Variant value giveaway")
+        .expect("position startpos moves e2e3 b7b5")
+        .expect("go depth 1")
+        .send("info depth 1 score cp 100 pv f1b5")
+        .send("bestmove f1b5")
+        .expect("setoption name UCI_Variant value 3check")
+        .expect("position startpos")
+        .expect("go depth 1")
+        .send("info depth 1 score cp 10 pv e2e4")
+        .send("bestmove e2e4")
+        .spawn();
+    engine.uci().await.unwrap();
+    assert_eq!(engine.supported_options().len(), 1);
+
+    let job = Go::default().moves(&["e2e3", "b7b5"]).depth(1);
+    engine.go(job.variant(Variant::Antichess)).await.unwrap();
+    assert_eq!(engine.variant(), Variant::Antichess);
+
+    let job = Go::default().variant(Variant::ThreeCheck).depth(1);
+    engine.go(job).await.unwrap();
+    assert!(engine.set_variant(Variant::Atomic).await.is_err());
+    mock.finish().await.unwrap();
+}
+
+#[test]
+fn drops_in_pv_and_bestmove() {
+    let info: Info = "info depth 5 score cp 40 pv P@e4 e7e5 N@f6"
+        .parse()
+        .unwrap();
+    let best: BestMove = "bestmove P@e4 ponder e7e5".parse().unwrap();
+    assert_eq!(info.pv, ["P@e4", "e7e5", "N@f6"]);
+    assert_eq!(best.best, "P@e4");
+
+    let fen = "rnb1kbnr/ppp1pppp/8/8/8/2N5/PPPP1PPP/R1BQKBNR/Pp w KQkq - 0 4";
+    let pos = variant::position(Variant::Crazyhouse, Some(fen)).unwrap();
+    let line = variant::line(&pos, &info.pv[..2]).unwrap();
+    assert_eq!(line.len(), 2);
+    assert!(variant::line(&pos, &["Q@e4".to_string()]).is_err());
+}
+
+#[tokio::test]
+async fn pgn_variant_header() {
+    let games = pgn::read_games(
+        &br#"[Variant "Crazyhouse"]
+
+1. e4 d5 2. exd5 Qxd5 3. Nc3 Qd8 4. P@d4 *
+
+[Variant "Three-check"]
+[FEN "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 3+3 0 1"]
+
+1. e4 e5 *
+"#[..],
+    )
+    .unwrap();
+
+    assert_eq!(games.len(), 2);
+    let zh = &games[0];
+    assert_eq!(zh.variant, Variant::Crazyhouse);
+    assert_eq!(zh.moves[6].uci, "P@d4");
+    assert_eq!(zh.moves[6].san, "@d4");
+    assert_eq!(games[1].variant, Variant::ThreeCheck);
+    assert_eq!(games[1].start().unwrap().variant(), Variant::ThreeCheck);
+    assert_eq!(
+        format!("{:?}", games[1].go(10)),
+        format!("{:?}", games[1].go(2))
+    );
+
+    let (mut engine, mock) = Script::new()
+        .expect("uci")
+        .send(VARIANTS)
+        .send("uciok")
+        .expect("setoption name UCI_Variant value crazyhouse")
+        .expect("position startpos moves e2e4 d7d5 e4d5 d8d5 b1c3 d5d8 P@d4")
+        .expect("go depth 1")
+        .send("info depth 1 score cp 60 pv N@e4")
+        .send("bestmove N@e4")
+        .spawn();
+    engine.uci().await.unwrap();
+    let (_, best) = engine.go(zh.go(7).depth(1)).await.unwrap();
+    assert_eq!(best.best, "N@e4");
+    mock.finish().await.unwrap();
+
+    assert!(pgn::read_games(&b"1. e4 e5 2. Ke3 *"[..]).is_err());
+    assert!(pgn::read_games(&b"[Variant \"Shogi\"]\n\n1. e4 *"[..]).is_err());
+}
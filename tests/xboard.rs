@@ -0,0 +1,170 @@
+use std::time::Duration;
+
+use shakmaty::variant::Variant;
+use uci::{
+    engine::Go,
+    mock::Script,
+    search::{BestMove, Score, Search},
+    xboard::XBoard,
+};
+
+fn handshake(script: Script) -> Script {
+    script
+        .expect("xboard")
+        .expect("protover 2")
+        .send(r#"feature ping=1 setboard=1 usermove=1 san=1 myname="Mock XB 1.0" done=0"#)
+        .expect("accepted ping")
+        .expect("accepted setboard")
+        .expect("accepted usermove")
+        .expect("rejected san")
+        .expect("accepted myname")
+        .expect("accepted done")
+        .send(r#"feature option="Hash -spin 64 1 4096" done=1"#)
+        .expect("accepted option")
+        .expect("accepted done")
+        .expect("easy")
+}
+
+#[tokio::test]
+async fn thinking_output_and_moves() {
+    let (engine, mock) = handshake(Script::new())
+        .expect("new")
+        .expect("force")
+        .expect("usermove e2e4")
+        .expect("sd 3")
+        .expect("post")
+        .expect("go")
+        .send("1 20 5 100 Nf6")
+        .send("Hint: Nf6")
+        .send("3 -35 120 4500 1. ... Nf6 2. Nc3 d5")
+        .send("move Nf6")
+        .expect("new")
+        .expect("force")
+        .expect("setboard rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1")
+        .expect("st 2")
+        .expect("post")
+        .expect("go")
+        .send("5 -99997 30 900 7 30000 0\te7e5 Nf3 Qxe5")
+        .send("move e7e5")
+        .spawn();
+    let mut engine = XBoard::new(engine).await.unwrap();
+    assert_eq!(engine.name(), Some("Mock XB 1.0"));
+    assert_eq!(engine.feature("san"), None);
+    assert_eq!(engine.supported_options(), ["Hash -spin 64 1 4096"]);
+
+    let mut infos = 0;
+    let job = Go::default().moves(&["e2e4"]).depth(3);
+    let (info, best) = engine.go_with(job, |_| infos += 1).await.unwrap();
+    assert_eq!(infos, 2);
+    assert_eq!((info.depth, info.score), (3, Some(Score::Cp(-35))));
+    assert_eq!((info.time, info.nodes), (Some(1200), Some(4500)));
+    assert_eq!(info.pv, ["g8f6", "b1c3", "d7d5"]);
+    assert_eq!(best.best, "g8f6");
+
+    let fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1";
+    let (info, best) = engine
+        .go(Go::default().fen(fen).movetime(1500))
+        .await
+        .unwrap();
+    assert_eq!(info.score, Some(Score::Mate(-3)));
+    assert_eq!((info.seldepth, info.nps), (Some(7), Some(30000)));
+    // The line is cut at the first illegal move.
+    assert_eq!(info.pv, ["e7e5", "g1f3"]);
+    assert_eq!(best.best, "e7e5");
+    mock.finish().await.unwrap();
+}
+
+#[tokio::test]
+async fn stop_analysis_and_thinking() {
+    let (engine, mock) = handshake(Script::new())
+        .expect("new")
+        .expect("force")
+        .expect("post")
+        .expect("analyze")
+        .send("4 15 10 2000 e2e4 e7e5")
+        .expect("exit")
+        .expect("ping 1")
+        .send("pong 1")
+        .expect("new")
+        .expect("force")
+        .expect("sd 20")
+        .expect("level 40 5 2.5")
+        .expect("post")
+        .expect("go")
+        .send("1 10 1 20 d2d4")
+        .expect("?")
+        .send("move d2d4")
+        .expect("ping 2")
+        .send("pong 2")
+        .expect("ping 3")
+        .send("pong 3")
+        .spawn();
+    let mut engine = XBoard::new(engine).await.unwrap();
+
+    engine.start(Go::default()).await.unwrap();
+    assert!(matches!(engine.recv().await.unwrap(), Search::Info(_)));
+    let (info, best) = engine.stop().await.unwrap().unwrap();
+    assert_eq!(info.depth, 4);
+    assert_eq!(
+        best,
+        BestMove {
+            best: "e2e4".into(),
+            ponder: Some("e7e5".into())
+        }
+    );
+
+    engine.level(40, Duration::from_secs(300), Duration::from_millis(2500));
+    engine.start(Go::default().depth(20)).await.unwrap();
+    assert!(matches!(engine.recv().await.unwrap(), Search::Info(_)));
+    let (info, best) = engine.stop().await.unwrap().unwrap();
+    assert_eq!((info.pv[0].as_str(), best.best.as_str()), ("d2d4", "d2d4"));
+    assert!(engine.stop().await.unwrap().is_none());
+
+    let job = Go::new().variant(Variant::Crazyhouse);
+    assert!(engine.start(job).await.is_err());
+    mock.finish().await.unwrap();
+}
+
+#[tokio::test]
+async fn stop_analysis_without_ping() {
+    let (engine, mock) = Script::new()
+        .expect("xboard")
+        .expect("protover 2")
+        .send("feature setboard=1 done=1")
+        .expect("accepted setboard")
+        .expect("accepted done")
+        .expect("easy")
+        .expect("new")
+        .expect("force")
+        .expect("post")
+        .expect("analyze")
+        .send("4 15 10 2000 e2e4 e7e5")
+        .expect("exit")
+        // Still in flight when the analysis was stopped.
+        .send("5 99998 12 3000 d2d4")
+        .expect("new")
+        .expect("force")
+        .expect("sd 1")
+        .expect("post")
+        .expect("go")
+        .send("1 10 1 20 e2e4")
+        .send("move e2e4")
+        .spawn();
+    let mut engine = XBoard::new(engine).await.unwrap();
+
+    engine.start(Go::default()).await.unwrap();
+    assert!(matches!(engine.recv().await.unwrap(), Search::Info(_)));
+    let (info, _) = engine.stop().await.unwrap().unwrap();
+    assert_eq!(info.depth, 4);
+
+    let mut depths = Vec::new();
+    let job = Go::default().depth(1);
+    let (info, best) = engine
+        .go_with(job, |info| depths.push(info.depth))
+        .await
+        .unwrap();
+    assert_eq!(depths, [1]);
+    assert_eq!(info.score, Some(Score::Cp(10)));
+    assert_eq!(best.best, "e2e4");
+    mock.finish().await.unwrap();
+}